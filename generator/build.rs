@@ -1,6 +1,7 @@
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use anyhow::Context as _;
 use lightningcss::{
@@ -24,23 +25,83 @@ fn main() -> anyhow::Result<()> {
         fs::remove_dir_all(&out_static_dir).context("failed to remove all generated static/")?;
     }
 
-    let is_css_dir = |p: &Path| p.is_dir() && p.file_name().and_then(|x| x.to_str()) == Some("css");
+    let ignored_dirs = read_build_ignore(&src_static_dir).context("failed to read .buildignore")?;
+    let should_skip = |p: &Path| {
+        let file_name = p.file_name().and_then(|x| x.to_str());
+        file_name.is_some_and(|x| x == BUILD_IGNORE_FILE || x == CSS_BUNDLE_FILE)
+            || (p.is_dir() && ignored_dirs.iter().any(|x| x == p))
+    };
     fs::create_dir_all(&out_static_dir)?;
-    copy_dir(&src_static_dir, &out_static_dir, is_css_dir).context("failed to copy static/")?;
+    copy_dir(&src_static_dir, &out_static_dir, &should_skip).context("failed to copy static/")?;
 
-    build_css(
-        src_static_dir.join("css/main.css"),
-        out_static_dir.join("styles.css"),
-    )
-    .context("failed to generate static/styles.css")?;
+    let css_entries =
+        read_css_bundle(&src_static_dir, &out_static_dir).context("failed to read .cssbundle")?;
+    build_css(&css_entries).context("failed to generate css")?;
+    check_theme_vars(&src_static_dir).context("failed to check the theme variables")?;
 
     Ok(())
 }
 
+/// List of dirs in `static/` that should not be copied as-is
+const BUILD_IGNORE_FILE: &str = ".buildignore";
+
+/// Read the dirs listed in `static/.buildignore`, defaults to `css`.
+fn read_build_ignore(static_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let path = static_dir.join(BUILD_IGNORE_FILE);
+
+    if !path.exists() {
+        return Ok(vec![static_dir.join("css")]);
+    }
+
+    let content = fs::read_to_string(path)?;
+
+    let dirs = content
+        .lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(|x| static_dir.join(x.trim_matches('/')))
+        .collect();
+
+    Ok(dirs)
+}
+
+/// List of css entries to bundle
+const CSS_BUNDLE_FILE: &str = ".cssbundle";
+
+/// Read the `(entry, output)` pairs listed in `static/.cssbundle`,
+/// defaults to `css/main.css` -> `styles.css`.
+fn read_css_bundle(
+    static_dir: &Path,
+    out_static_dir: &Path,
+) -> anyhow::Result<Vec<(PathBuf, PathBuf)>> {
+    let path = static_dir.join(CSS_BUNDLE_FILE);
+
+    if !path.exists() {
+        return Ok(vec![(
+            static_dir.join("css/main.css"),
+            out_static_dir.join("styles.css"),
+        )]);
+    }
+
+    let content = fs::read_to_string(path)?;
+
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(
+            |x| match x.split_whitespace().collect::<Vec<_>>().as_slice() {
+                &[entry, output] => Ok((static_dir.join(entry), out_static_dir.join(output))),
+                _ => Err(anyhow::anyhow!("expected `<entry> <output>`, found: `{x}`")),
+            },
+        )
+        .collect()
+}
+
 fn copy_dir(
     source_dir: impl AsRef<Path>,
     dest_dir: impl AsRef<Path>,
-    should_skip: fn(&Path) -> bool,
+    should_skip: &impl Fn(&Path) -> bool,
 ) -> std::io::Result<()> {
     let source_dir = source_dir.as_ref();
     let dest_dir = dest_dir.as_ref();
@@ -57,32 +118,112 @@ fn copy_dir(
             copy_dir(entry.path(), dest_dir.join(entry.file_name()), should_skip)?;
         } else {
             fs::create_dir_all(dest_dir)?;
-            fs::copy(entry.path(), dest_dir.join(entry.file_name()))?;
+            copy_file(entry.path(), dest_dir.join(entry.file_name()))?;
         }
     }
 
     Ok(())
 }
 
-pub fn build_css(
-    input_path: impl AsRef<Path>,
-    output_path: impl AsRef<Path>,
-) -> anyhow::Result<()> {
-    let fs = FileProvider::new();
-    let mut bundler = Bundler::new(&fs, None, ParserOptions::default());
+/// Copy the file with its modification time, embedded with the file for mtime based caching
+fn copy_file(from: impl AsRef<Path>, to: impl AsRef<Path>) -> std::io::Result<()> {
+    let metadata = fs::metadata(&from)?;
+    let mut to = fs::File::create(to)?;
+    std::io::copy(&mut fs::File::open(from)?, &mut to)?;
 
-    let mut stylesheet = bundler
-        .bundle(input_path.as_ref())
-        .map_err(|e| anyhow::anyhow!("failed to build stylesheet: {:?}", e))?;
+    // the sources may be read-only (e.g. in the cargo registry), the copy stays writable
+    to.set_modified(metadata.modified()?)
+}
 
-    stylesheet.minify(MinifyOptions::default())?;
+/// Newest modification time of the files in `dir`, recursively
+fn newest_modified(dir: &Path) -> std::io::Result<Option<SystemTime>> {
+    let mut newest = None;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let modified = if entry.file_type()?.is_dir() {
+            newest_modified(&entry.path())?
+        } else {
+            Some(entry.metadata()?.modified()?)
+        };
+        newest = newest.max(modified);
+    }
+
+    Ok(newest)
+}
+
+/// Palette variables of the default theme in `css/main.css`
+const THEME_VAR_PREFIX: &str = "--base";
+
+/// Every `[data-theme]` block in `css/theme.css` must define all the palette variables
+/// of `css/main.css`, otherwise the missing ones silently fall back to the default theme.
+fn check_theme_vars(static_dir: &Path) -> anyhow::Result<()> {
+    let theme_path = static_dir.join("css/theme.css");
+    if !theme_path.exists() {
+        return Ok(());
+    }
+
+    let declared = |css: &str| -> Vec<String> {
+        css.lines()
+            .filter_map(|x| x.trim().split_once(':'))
+            .map(|(name, _)| name.trim().to_string())
+            .filter(|x| x.starts_with(THEME_VAR_PREFIX))
+            .collect()
+    };
+
+    let palette = declared(&fs::read_to_string(static_dir.join("css/main.css"))?);
+    let theme = fs::read_to_string(theme_path)?;
+
+    for block in theme.split("[data-theme=").skip(1) {
+        let (selector, rest) = block.split_once(']').unwrap_or((block, ""));
+        let vars = declared(rest.split('}').next().unwrap_or_default());
+
+        let missing: Vec<_> = palette.iter().filter(|x| !vars.contains(x)).collect();
+        if !missing.is_empty() {
+            return Err(anyhow::anyhow!(
+                "`[data-theme={selector}]` misses {missing:?}"
+            ));
+        }
+    }
+
+    Ok(())
+}
 
-    let res = stylesheet.to_css(PrinterOptions {
-        minify: true,
-        ..Default::default()
-    })?;
+/// Bundle and minify each `(entry, output)`
+pub fn build_css(entries: &[(PathBuf, PathBuf)]) -> anyhow::Result<()> {
+    let fs = FileProvider::new();
+    let mut bundler = Bundler::new(&fs, None, ParserOptions::default());
 
-    fs::write(output_path, res.code)?;
+    for (input_path, output_path) in entries {
+        let mut stylesheet = bundler.bundle(input_path).map_err(|e| {
+            anyhow::anyhow!(
+                "failed to build stylesheet {}: {:?}",
+                input_path.display(),
+                e
+            )
+        })?;
+
+        stylesheet.minify(MinifyOptions::default())?;
+
+        let res = stylesheet.to_css(PrinterOptions {
+            minify: true,
+            ..Default::default()
+        })?;
+
+        fs::write(output_path, res.code)?;
+
+        // the newest of the bundled sources, they are in the dir of the entry
+        if let Some(modified) = input_path
+            .parent()
+            .map(newest_modified)
+            .transpose()?
+            .flatten()
+        {
+            fs::File::options()
+                .write(true)
+                .open(output_path)?
+                .set_modified(modified)?;
+        }
+    }
 
     Ok(())
 }
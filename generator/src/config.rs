@@ -1,6 +1,6 @@
 use std::{
-    collections::HashSet,
-    fs,
+    collections::BTreeMap,
+    fs, io,
     path::{Path, PathBuf},
 };
 
@@ -13,21 +13,587 @@ pub struct Config {
     pub site_name: String,
     pub site_url: String,
     pub commit_base_url: String,
+    /// Path the site is hosted under (e.g. `/notes` for `https://example.com/notes/`),
+    /// prepended to all internal urls, empty for the root
+    #[serde(default)]
+    pub base_path: String,
+    /// `<html lang>` of the pages, can be overridden by the frontmatter `lang`
+    #[serde(default = "default_lang")]
+    pub lang: String,
+    /// `{history_base_url}/{path}` is the history of a source file on the forge
+    /// (e.g. `https://github.com/user/repo/commits/main`), linked from the commit list
+    #[serde(default)]
+    pub history_base_url: Option<String>,
+    /// Max number of commits listed under each blog post, newest first
+    #[serde(default = "default_max_commits")]
+    pub max_commits: usize,
+    /// Max number of git operations at once, each one opens the pack files it reads.
+    /// The number of CPUs if not set, lower it if a large repo runs out of file descriptors
+    #[serde(default)]
+    pub git_concurrency: Option<usize>,
+    /// List the distinct git authors of each blog post
+    #[serde(default)]
+    pub contributors: bool,
+    /// Blog posts dated after today, `SOURCE_DATE_EPOCH` if set
+    #[serde(default)]
+    pub future_posts: FuturePosts,
+
+    /// Glob patterns (e.g. `drafts/**`, `*.tmp`) of paths to skip,
+    /// relative to the source dir
+    #[serde(default)]
+    pub skip: Vec<String>,
+
+    /// Blog feeds to generate
+    #[serde(default = "default_feeds")]
+    pub feeds: Vec<FeedFormat>,
+
+    /// Max number of the most recent posts in RSS, all posts if it's `0` or not set
+    #[serde(default)]
+    pub rss_max_items: Option<usize>,
+    /// Include the full post in `<content:encoded>` of RSS, otherwise only the description
+    #[serde(default = "default_true")]
+    pub rss_full_content: bool,
+
+    /// Also write the frontmatter `aliases` into a netlify-style `_redirects` file
+    #[serde(default)]
+    pub redirects_file: bool,
+
+    /// Comment widget under the blog posts, can be disabled by the frontmatter `comments: false`
+    #[serde(default)]
+    pub comments: Option<Comments>,
+
+    /// Feeds recommended on the `blogroll` page, also exported as `blogroll.opml`
+    #[serde(default)]
+    pub blogroll: Vec<BlogrollFeed>,
+
+    /// Generate `search-index.json` for client-side search
+    #[serde(default)]
+    pub search_index: bool,
+
+    /// Write `build-info.json` with the source commit and `humans.txt`
+    #[serde(default = "default_true")]
+    pub build_info: bool,
+
+    /// Generate the `blog/stats` page
+    #[serde(default)]
+    pub stats_page: bool,
+
+    /// Max number of related posts shown under each blog post
+    #[serde(default = "default_related_posts")]
+    pub related_posts: usize,
+
+    /// Syntect theme for code highlight (e.g. `InspiredGitHub`, `base16-ocean.dark`),
+    /// code is highlighted with CSS classes if not set
+    #[serde(default)]
+    pub syntax_theme: Option<String>,
+    /// Highlight code with CSS classes and generate `static/syntax.css` from `syntax_theme`
+    #[serde(default)]
+    pub syntax_css: bool,
+    /// Dir of `.sublime-syntax` files for the languages syntect doesn't have (e.g. Nix, Zig),
+    /// relative to the source dir and not part of the site
+    #[serde(default)]
+    pub syntaxes_dir: Option<PathBuf>,
+
+    /// Check internal links in the generated pages
+    #[serde(default)]
+    pub link_check: CheckLevel,
+    /// Also check `#anchor` of internal links against the element ids
+    #[serde(default)]
+    pub link_check_anchors: bool,
+    /// Check the local images and other assets in the articles
+    #[serde(default)]
+    pub asset_check: CheckLevel,
+
+    /// Url pattern of the blog posts with `:year`, `:month`, `:day`, `:slug` and `:name`
+    /// (the frontmatter `slug` or the file name `yyyy-mm-dd-slug`), or one of the presets:
+    /// `name` (`/blog/:name`), `date` (`/blog/:year/:month/:day/:slug`), `slug` (`/blog/:slug`)
+    #[serde(default = "default_permalink")]
+    pub permalink: String,
+
+    /// Output `abc/index.html` for `abc.md`, otherwise `abc.html`
+    #[serde(default = "default_true")]
+    pub pretty_urls: bool,
+    /// Whether the pretty urls end with `/`, shared with the web server which
+    /// redirects the other form
+    #[serde(default)]
+    pub trailing_slash: my_site_web::TrailingSlash,
+
+    /// Template of the page titles with `{title}`, `{site_name}` and `{author}`,
+    /// just the title if empty, can be overridden by the frontmatter `title_suffix`
+    #[serde(default = "default_title_suffix")]
+    pub title_suffix: String,
+
+    /// Markdown extensions to enable or disable over the defaults (e.g. `tasklist: false`,
+    /// `wikilinks: true`), unknown names are warned
+    #[serde(default)]
+    pub markdown: BTreeMap<String, bool>,
+
+    /// How the emoji shortcodes (e.g. `:smile:`) are rendered
+    #[serde(default)]
+    pub emoji: EmojiStyle,
+
+    /// Line numbers on all code blocks, or per block with `linenos` (e.g. ```` ```rust,linenos ````),
+    /// lines can be highlighted per block with e.g. ```` ```rust {2,5-7} ````
+    #[serde(default)]
+    pub code_line_numbers: bool,
+
+    /// Open the links to other sites in a new tab with `rel="noopener noreferrer"`
+    #[serde(default = "default_true")]
+    pub external_links: bool,
+    /// Also mark the external links with an arrow
+    #[serde(default)]
+    pub external_link_marker: bool,
+
+    /// Heading of the footnotes section in articles
+    #[serde(default = "default_footnotes_heading")]
+    pub footnotes_heading: String,
+
+    /// Symbol of the anchor links on the headings, no anchor links if empty
+    #[serde(default = "default_heading_anchor")]
+    pub heading_anchor: String,
+    /// Levels of the headings with anchor links, the others still get their `id`
+    #[serde(default = "default_anchor_min_level")]
+    pub anchor_min_level: u8,
+    #[serde(default = "default_anchor_max_level")]
+    pub anchor_max_level: u8,
+
+    /// `strftime` format of the displayed dates
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+
+    /// Minify the html output
+    #[serde(default = "default_true")]
+    pub minify: bool,
+    #[serde(default)]
+    pub minify_options: MinifyConfig,
+
+    /// `<link rel="icon">` entries in the head, at least one is required
+    #[serde(default = "default_favicon")]
+    pub favicon: Vec<Icon>,
+
+    /// Resized variants of the local images for `srcset`, slows down the build
+    #[serde(default)]
+    pub responsive_images: ResponsiveImages,
+    /// Blurred placeholders of the local images until they are loaded, slows down the build
+    #[serde(default)]
+    pub image_placeholders: bool,
+
+    /// `<meta name="viewport">` of the pages
+    #[serde(default = "default_viewport")]
+    pub viewport: String,
+    /// `<meta name="theme-color">`, can be overridden by the frontmatter `theme_color`
+    #[serde(default)]
+    pub theme_color: Option<ThemeColor>,
 
+    /// Light theme by `prefers-color-scheme` and a toggle button in the header,
+    /// only the dark theme otherwise
     #[serde(default)]
-    pub skip: HashSet<PathBuf>,
+    pub theme_toggle: bool,
+
+    /// Stylesheet for printing, without the navigation and with the urls of the links
+    #[serde(default = "default_true")]
+    pub print_stylesheet: bool,
+
+    /// `preload`, `preconnect` and `dns-prefetch` hints in the head
+    #[serde(default)]
+    pub head_hints: HeadHints,
+
+    /// Markdown files of the home, not found and error pages
+    #[serde(default)]
+    pub special_pages: SpecialPages,
 
     pub header: Header,
     pub footer: Footer,
 }
 
-pub const HOME_MD: &str = "home.md";
-pub const NOT_FOUND_MD: &str = "not_found.md";
+/// Config files in the source dir by priority, YAML first if multiple exist
+pub const CONFIG_FILES: &[&str] = &["config.yaml", "config.toml", "config.json"];
 pub const BLOG_DIR: &str = "blog";
 pub const STATIC_DIR: &str = "static";
+/// `header.html` and `footer.html` replacing the built-in ones, not part of the output
+pub const PARTIALS_DIR: &str = "partials";
+
+pub fn tag_to_link(tag: &str, style: UrlStyle) -> String {
+    page_link(format!("blog/tags/{}", slugify_tag(tag)), style)
+}
+
+/// `blog/tags/{tag}/rss.xml`
+pub fn tag_feed_path(tag: &str) -> String {
+    format!("blog/tags/{}/rss.xml", slugify_tag(tag))
+}
+
+/// `C++/Machine Learning` -> `cplusplus/machine-learning`, the url path of a tag
+pub fn slugify_tag(tag: &str) -> String {
+    normalize_tag(tag)
+        .split('/')
+        .map(slugify)
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Lowercase alphanumerics separated by `-`, `+` and `#` are spelled out for `C++` and `C#`
+fn slugify(s: &str) -> String {
+    let mut slug = String::new();
+    for c in s.chars() {
+        match c {
+            '+' => slug.push_str("plus"),
+            '#' => slug.push_str("sharp"),
+            c if c.is_alphanumeric() => slug.extend(c.to_lowercase()),
+            c if (c.is_whitespace() || matches!(c, '-' | '_' | '.'))
+                && !slug.is_empty()
+                && !slug.ends_with('-') =>
+            {
+                slug.push('-');
+            }
+            _ => {}
+        }
+    }
+
+    slug.trim_end_matches('-').to_string()
+}
+
+/// `/rust//async/` -> `rust/async`
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split('/')
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `rust/async` -> `rust`, `rust/async`
+pub fn tag_ancestors(tag: &str) -> Vec<String> {
+    let tag = normalize_tag(tag);
+    tag.match_indices('/')
+        .map(|(i, _)| tag[..i].to_string())
+        .chain(std::iter::once(tag.clone()))
+        .collect()
+}
+
+/// `Rust Web` -> `/blog/series/rust-web/`, slugified as the tags
+pub fn series_to_link(series: &str, style: UrlStyle) -> String {
+    page_link(format!("blog/series/{}", slugify_tag(series)), style)
+}
+
+/// Expand the presets of the `permalink` config
+pub fn permalink_pattern(permalink: &str) -> &str {
+    match permalink {
+        "name" => "/blog/:name",
+        "date" => "/blog/:year/:month/:day/:slug",
+        "slug" => "/blog/:slug",
+        pattern => pattern,
+    }
+}
+
+/// Relative path of a blog post from the `permalink` config,
+/// e.g. `blog/2025/01/02/abc` for `/blog/:year/:month/:day/:slug`
+///
+/// `name` is the frontmatter `slug` or the file name `yyyy-mm-dd-slug`
+pub fn blog_permalink(permalink: &str, date: chrono::NaiveDate, slug: &str, name: &str) -> PathBuf {
+    permalink_pattern(permalink)
+        .replace(":year", &date.format("%Y").to_string())
+        .replace(":month", &date.format("%m").to_string())
+        .replace(":day", &date.format("%d").to_string())
+        .replace(":slug", slug)
+        .replace(":name", name)
+        .trim_matches('/')
+        .into()
+}
+
+/// How the page links are formed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UrlStyle {
+    /// `/blog/abc` instead of `/blog/abc.html`
+    pub pretty_urls: bool,
+    /// `/blog/abc/` with pretty urls
+    pub trailing_slash: bool,
+}
+
+/// `blog/abc` -> `/blog/abc` or `/blog/abc/` by the trailing slash policy,
+/// or `/blog/abc.html` without pretty urls.
+///
+/// The landing page of a section `docs/index` links to the dir `/docs/`,
+/// or `/docs/index.html` without pretty urls.
+pub fn page_link(rel_path: impl AsRef<Path>, style: UrlStyle) -> String {
+    let rel_path = rel_path.as_ref();
+    let link = Path::new("/").join(rel_path);
+    let link = link.to_str().unwrap_or("/");
+
+    if let Some(dir) = index_dir(rel_path) {
+        let dir = Path::new("/").join(dir);
+        let dir = dir.to_str().unwrap_or("/").trim_end_matches('/');
+        return if !style.pretty_urls {
+            format!("{dir}/index.html")
+        } else if style.trailing_slash || dir.is_empty() {
+            format!("{dir}/")
+        } else {
+            dir.to_string()
+        };
+    }
+
+    if !style.pretty_urls {
+        format!("{link}.html")
+    } else if style.trailing_slash && !link.ends_with('/') {
+        format!("{link}/")
+    } else {
+        link.to_string()
+    }
+}
+
+/// `blog/abc` -> `blog/abc/index.html`, or `blog/abc.html` without pretty urls,
+/// `docs/index` -> `docs/index.html` either way
+pub fn page_file(rel_path: impl AsRef<Path>, pretty_urls: bool) -> PathBuf {
+    let rel_path = rel_path.as_ref();
+
+    if let Some(dir) = index_dir(rel_path) {
+        dir.join("index.html")
+    } else if pretty_urls {
+        rel_path.join("index.html")
+    } else {
+        // not `with_extension`, the slug may contain dots
+        let mut file = rel_path.as_os_str().to_owned();
+        file.push(".html");
+        file.into()
+    }
+}
+
+/// `docs/index` -> `docs`, the dir of the `index.md` landing page
+fn index_dir(rel_path: &Path) -> Option<&Path> {
+    if rel_path.file_name()? != "index" {
+        return None;
+    }
+    rel_path.parent()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+    Json,
+}
+
+impl FeedFormat {
+    /// Output path relative to the output dir
+    pub fn path(self) -> &'static str {
+        match self {
+            Self::Rss => "blog/rss.xml",
+            Self::Atom => "blog/atom.xml",
+            Self::Json => "blog/feed.json",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Rss => "application/rss+xml",
+            Self::Atom => "application/atom+xml",
+            Self::Json => "application/feed+json",
+        }
+    }
+}
+
+/// What to do with the blog posts whose file name date is after today
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FuturePosts {
+    /// Scheduled posts, left out until the date
+    Skip,
+    #[default]
+    Warn,
+    Publish,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckLevel {
+    Off,
+    #[default]
+    Warn,
+    Error,
+}
 
-pub fn tag_to_link(tag: &str) -> String {
-    format!("/blog/tags/{tag}")
+/// Options of minify-html, whitespace in `<pre>` is always kept
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MinifyConfig {
+    pub keep_comments: bool,
+    pub keep_closing_tags: bool,
+    pub keep_html_and_head_opening_tags: bool,
+    /// Minify the inline `<style>`
+    pub minify_css: bool,
+    /// Minify the inline `<script>`
+    pub minify_js: bool,
+}
+
+/// Source markdown files of the special pages, relative to the source dir
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpecialPages {
+    /// Copied to `index.html`
+    #[serde(default = "default_home_md_path")]
+    pub home: PathBuf,
+    /// Copied to the not found page of the web server
+    #[serde(default = "default_not_found_md_path")]
+    pub not_found: PathBuf,
+    /// Copied to the error page of the web server, optional
+    #[serde(default = "default_error_md_path")]
+    pub error: PathBuf,
+}
+
+impl Default for SpecialPages {
+    fn default() -> Self {
+        Self {
+            home: default_home_md_path(),
+            not_found: default_not_found_md_path(),
+            error: default_error_md_path(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Icon {
+    /// `icon`, `apple-touch-icon`, etc.
+    #[serde(default = "default_icon_rel")]
+    pub rel: String,
+    pub href: String,
+    /// e.g. `32x32`, `any`
+    #[serde(default)]
+    pub sizes: Option<String>,
+    /// e.g. `image/svg+xml`, `image/x-icon`
+    #[serde(default, rename = "type")]
+    pub mime_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ResponsiveImages {
+    pub enabled: bool,
+    /// Widths of the variants, only the ones narrower than the original are generated
+    pub widths: Vec<u32>,
+    /// `sizes` of the images
+    pub sizes: String,
+}
+
+impl Default for ResponsiveImages {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            widths: vec![480, 960, 1440],
+            sizes: "(max-width: 50em) 100vw, 50em".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiStyle {
+    /// The emoji characters
+    #[default]
+    Unicode,
+    /// Twemoji images for the same look on all platforms
+    Twemoji,
+    /// Keep the shortcodes as text
+    Off,
+}
+
+/// A color for all, or light and dark colors by `prefers-color-scheme`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ThemeColor {
+    Single(String),
+    Scheme { light: String, dark: String },
+}
+
+/// Body structure of a page, by the frontmatter `layout`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// Header, content and footer in a narrow column
+    #[default]
+    Default,
+    /// The content spans the window, e.g. for wide tables and images
+    Wide,
+    /// Only the content without the header and footer, e.g. for landing pages
+    Minimal,
+}
+
+impl Layout {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::Default),
+            "wide" => Some(Self::Wide),
+            "minimal" => Some(Self::Minimal),
+            _ => None,
+        }
+    }
+
+    /// Class of the `<body>`
+    pub fn class(self) -> &'static str {
+        match self {
+            Self::Default => "layout-default",
+            Self::Wide => "layout-wide",
+            Self::Minimal => "layout-minimal",
+        }
+    }
+}
+
+/// Resource hints in the head, origins are only hinted on the pages loading something from them
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HeadHints {
+    /// Preload the main stylesheet
+    pub preload_stylesheet: bool,
+    /// Origins to `preconnect` (e.g. `https://cdn.jsdelivr.net`)
+    pub preconnect: Vec<HintOrigin>,
+    /// Origins to `dns-prefetch`
+    pub dns_prefetch: Vec<String>,
+    /// Urls of the static assets to preload, stylesheets and scripts only on the pages using them,
+    /// others (e.g. `/static/fonts/inter.woff2`) on all pages
+    pub preload: Vec<String>,
+}
+
+impl Default for HeadHints {
+    fn default() -> Self {
+        Self {
+            preload_stylesheet: true,
+            preconnect: vec![],
+            dns_prefetch: vec![],
+            preload: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HintOrigin {
+    pub origin: String,
+    /// Connect in CORS mode, needed for fonts and module scripts
+    #[serde(default)]
+    pub crossorigin: bool,
+}
+
+/// Script of a comment system like giscus or utterances, its origin needs to be
+/// allowed in `script-src` and `frame-src` of the `Content-Security-Policy` of the
+/// web server, which the default policy doesn't (see `web/README.md`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct Comments {
+    /// e.g. `https://giscus.app/client.js`
+    pub script: String,
+    /// Attributes of the script, e.g. `data-repo` and `data-mapping: specific` for giscus
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+    /// Attribute of the script the post id is passed in, e.g. `issue-term` for utterances
+    #[serde(default = "default_comment_id_attribute")]
+    pub id_attribute: String,
+}
+
+/// An `<outline>` of the OPML
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlogrollFeed {
+    pub title: String,
+    /// Url of the feed
+    #[serde(alias = "xmlUrl")]
+    pub xml_url: String,
+    /// Url of the site
+    #[serde(default, alias = "htmlUrl")]
+    pub html_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -48,15 +614,253 @@ pub struct Link {
     pub url: String,
 }
 
+impl Default for Config {
+    /// The defaults of a config file with only the required fields, left empty
+    fn default() -> Self {
+        serde_json::from_value(serde_json::json!({
+            "author": "",
+            "author_email": "",
+            "site_name": "",
+            "site_url": "",
+            "commit_base_url": "",
+            "header": { "home_name": "", "links": [] },
+            "footer": { "links": [], "cc": "" },
+        }))
+        .expect("the required fields are all given")
+    }
+}
+
 impl Config {
+    /// Parse the config by the file extension: `yaml`, `toml` or `json`
     pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let file = fs::File::open(path)?;
-        Ok(serde_yaml::from_reader(file)?)
+        let path = path.as_ref();
+
+        let config: Self = match path.extension().and_then(|x| x.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_reader(fs::File::open(path)?)?,
+            Some("toml") => toml::from_str(&fs::read_to_string(path)?)?,
+            Some("json") => serde_json::from_reader(io::BufReader::new(fs::File::open(path)?))?,
+            _ => {
+                return Err(anyhow::anyhow!("unknown config format: {}", path.display()));
+            }
+        };
+
+        Ok(config)
     }
+
+    /// The first existing file of `CONFIG_FILES` in `src_dir`, relative to `src_dir`
+    pub fn find_file(src_dir: impl AsRef<Path>) -> anyhow::Result<&'static Path> {
+        let src_dir = src_dir.as_ref();
+
+        for file in CONFIG_FILES {
+            if src_dir.join(file).try_exists()? {
+                return Ok(Path::new(file));
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "config not found, expected one of: {}",
+            CONFIG_FILES.join(", ")
+        ))
+    }
+
+    pub fn url_style(&self) -> UrlStyle {
+        UrlStyle {
+            pretty_urls: self.pretty_urls,
+            trailing_slash: self.trailing_slash == my_site_web::TrailingSlash::Always,
+        }
+    }
+
+    /// Check the values serde cannot, and report every problem at once
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut problems = Vec::new();
+
+        let required = [
+            ("author", &self.author),
+            ("site_name", &self.site_name),
+            ("header.home_name", &self.header.home_name),
+            ("footer.cc", &self.footer.cc),
+        ];
+        for (field, value) in required {
+            if value.trim().is_empty() {
+                problems.push(format!("{field}: must not be empty"));
+            }
+        }
+
+        if self.git_concurrency == Some(0) {
+            problems.push("git_concurrency: must be at least 1".to_string());
+        }
+
+        let links = [
+            ("header.links", &self.header.links),
+            ("footer.links", &self.footer.links),
+        ];
+        for (field, links) in links {
+            for (i, link) in links.iter().enumerate() {
+                if link.title.trim().is_empty() {
+                    problems.push(format!("{field}[{i}].title: must not be empty"));
+                }
+                if link.url.trim().is_empty() {
+                    problems.push(format!("{field}[{i}].url: must not be empty"));
+                }
+            }
+        }
+
+        if !crate::markdown::is_absolute_url(&self.site_url)
+            || self.site_url.contains(char::is_whitespace)
+        {
+            problems.push(format!(
+                "site_url: `{}` is not an absolute http(s) url",
+                self.site_url
+            ));
+        }
+
+        if !(1..=self.anchor_max_level).contains(&self.anchor_min_level)
+            || self.anchor_max_level > 6
+        {
+            problems.push(format!(
+                "anchor_min_level, anchor_max_level: `{}..={}` is not a range of 1 to 6",
+                self.anchor_min_level, self.anchor_max_level
+            ));
+        }
+
+        if !self.base_path.is_empty()
+            && (!self.base_path.starts_with('/')
+                || self.base_path.ends_with('/')
+                || self.base_path.contains(['?', '#', ' ']))
+        {
+            problems.push(format!(
+                "base_path: `{}` must start and not end with `/`, e.g. `/notes`",
+                self.base_path
+            ));
+        }
+
+        if !is_email(&self.author_email) {
+            problems.push(format!(
+                "author_email: `{}` is not an email address",
+                self.author_email
+            ));
+        }
+
+        let is_invalid_date_format = chrono::format::StrftimeItems::new(&self.date_format)
+            .any(|x| matches!(x, chrono::format::Item::Error));
+        if is_invalid_date_format {
+            problems.push(format!("date_format: `{}` is invalid", self.date_format));
+        }
+
+        let permalink = permalink_pattern(&self.permalink);
+        if !permalink.starts_with('/') {
+            problems.push(format!(
+                "permalink: `{}` is neither a preset nor starts with `/`",
+                self.permalink
+            ));
+        } else if !permalink.contains(":slug") && !permalink.contains(":name") {
+            problems.push(format!(
+                "permalink: `{}` needs `:slug` or `:name` to be unique",
+                self.permalink
+            ));
+        }
+
+        if !self.title_suffix.is_empty() && !self.title_suffix.contains("{title}") {
+            problems.push(format!(
+                "title_suffix: `{}` needs `{{title}}`",
+                self.title_suffix
+            ));
+        }
+
+        if self.favicon.is_empty() {
+            problems.push("favicon: at least one icon is required".to_string());
+        }
+
+        for (i, feed) in self.blogroll.iter().enumerate() {
+            if feed.title.trim().is_empty() {
+                problems.push(format!("blogroll[{i}].title: must not be empty"));
+            }
+            if !crate::markdown::is_absolute_url(&feed.xml_url) {
+                problems.push(format!(
+                    "blogroll[{i}].xml_url: `{}` is not an absolute http(s) url",
+                    feed.xml_url
+                ));
+            }
+            if let Some(html_url) = feed
+                .html_url
+                .as_ref()
+                .filter(|x| !crate::markdown::is_absolute_url(x))
+            {
+                problems.push(format!(
+                    "blogroll[{i}].html_url: `{html_url}` is not an absolute http(s) url"
+                ));
+            }
+        }
+
+        let origins = self
+            .head_hints
+            .preconnect
+            .iter()
+            .map(|x| ("head_hints.preconnect", &x.origin))
+            .chain(
+                self.head_hints
+                    .dns_prefetch
+                    .iter()
+                    .map(|x| ("head_hints.dns_prefetch", x)),
+            );
+        for (field, origin) in origins {
+            if crate::pages::url_origin(origin) != Some(origin.as_str()) {
+                problems.push(format!(
+                    "{field}: `{origin}` is not an origin like `https://example.com`"
+                ));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "invalid config:\n  {}",
+                problems.join("\n  ")
+            ))
+        }
+    }
+}
+
+/// `name@example.com`, not a full RFC 5322 check
+fn is_email(s: &str) -> bool {
+    match s.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !s.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+fn default_comment_id_attribute() -> String {
+    "data-term".to_string()
+}
+
+fn default_viewport() -> String {
+    "width=device-width, initial-scale=1".to_string()
 }
 
 fn default_favicon_path() -> PathBuf {
-    "favicon.ico".into()
+    "favicon.svg".into()
+}
+fn default_favicon() -> Vec<Icon> {
+    vec![Icon {
+        rel: default_icon_rel(),
+        href: Path::new("/")
+            .join(default_favicon_path())
+            .to_string_lossy()
+            .into_owned(),
+        sizes: None,
+        mime_type: Some("image/svg+xml".into()),
+    }]
+}
+fn default_icon_rel() -> String {
+    "icon".into()
 }
 fn default_home_md_path() -> PathBuf {
     "home.md".into()
@@ -64,6 +868,198 @@ fn default_home_md_path() -> PathBuf {
 fn default_not_found_md_path() -> PathBuf {
     "not_found.md".into()
 }
+fn default_error_md_path() -> PathBuf {
+    "500.md".into()
+}
 fn default_blog_dir() -> PathBuf {
     "blog".into()
 }
+fn default_title_suffix() -> String {
+    "{title} - {author}".into()
+}
+fn default_true() -> bool {
+    true
+}
+fn default_heading_anchor() -> String {
+    "#".into()
+}
+fn default_anchor_min_level() -> u8 {
+    2
+}
+fn default_anchor_max_level() -> u8 {
+    3
+}
+fn default_date_format() -> String {
+    "%Y-%m-%d".into()
+}
+fn default_footnotes_heading() -> String {
+    "Notes".into()
+}
+fn default_lang() -> String {
+    "en".into()
+}
+fn default_permalink() -> String {
+    "name".into()
+}
+fn default_feeds() -> Vec<FeedFormat> {
+    vec![FeedFormat::Rss, FeedFormat::Json]
+}
+fn default_max_commits() -> usize {
+    5
+}
+fn default_related_posts() -> usize {
+    5
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_link_by_trailing_slash() {
+        let always = UrlStyle {
+            pretty_urls: true,
+            trailing_slash: true,
+        };
+        let never = UrlStyle {
+            pretty_urls: true,
+            trailing_slash: false,
+        };
+
+        assert_eq!(page_link("blog/abc", always), "/blog/abc/");
+        assert_eq!(page_link("blog/abc", never), "/blog/abc");
+        assert_eq!(page_link("docs/index", always), "/docs/");
+        assert_eq!(page_link("docs/index", never), "/docs");
+        // the home page keeps its slash either way
+        assert_eq!(page_link("index", never), "/");
+        assert_eq!(tag_to_link("rust", never), "/blog/tags/rust");
+        assert_eq!(series_to_link("Rust Web", always), "/blog/series/rust-web/");
+    }
+
+    #[test]
+    fn validate_each_field() {
+        let valid = || Config {
+            author: "a".to_string(),
+            author_email: "a@example.com".to_string(),
+            site_name: "site".to_string(),
+            site_url: "https://example.com".to_string(),
+            header: Header {
+                home_name: "home".to_string(),
+                links: Vec::new(),
+            },
+            footer: Footer {
+                links: Vec::new(),
+                cc: "cc".to_string(),
+            },
+            ..Default::default()
+        };
+        let link = || Link {
+            title: "t".to_string(),
+            url: "/t".to_string(),
+        };
+        let feed = || BlogrollFeed {
+            title: "t".to_string(),
+            xml_url: "https://other.com/rss.xml".to_string(),
+            html_url: None,
+        };
+        valid().validate().unwrap();
+
+        // the field in the error and a change that breaks it
+        type Break = fn(&mut Config);
+        let cases: &[(&str, Break)] = &[
+            ("author", |c| c.author = " ".to_string()),
+            ("site_name", |c| c.site_name.clear()),
+            ("header.home_name", |c| c.header.home_name.clear()),
+            ("footer.cc", |c| c.footer.cc.clear()),
+            ("git_concurrency", |c| c.git_concurrency = Some(0)),
+            ("header.links[0].title", |c| {
+                c.header.links = vec![Link {
+                    title: String::new(),
+                    url: "/t".to_string(),
+                }]
+            }),
+            ("footer.links[0].url", |c| {
+                c.footer.links = vec![Link {
+                    title: "t".to_string(),
+                    url: " ".to_string(),
+                }]
+            }),
+            ("site_url", |c| c.site_url = "example.com".to_string()),
+            ("site_url", |c| {
+                c.site_url = "https://example .com".to_string()
+            }),
+            ("anchor_min_level, anchor_max_level", |c| {
+                c.anchor_min_level = 0
+            }),
+            ("anchor_min_level, anchor_max_level", |c| {
+                c.anchor_min_level = 4;
+                c.anchor_max_level = 3;
+            }),
+            ("anchor_min_level, anchor_max_level", |c| {
+                c.anchor_max_level = 7
+            }),
+            ("base_path", |c| c.base_path = "notes".to_string()),
+            ("base_path", |c| c.base_path = "/notes/".to_string()),
+            ("base_path", |c| c.base_path = "/notes?a".to_string()),
+            ("author_email", |c| c.author_email = "a@example".to_string()),
+            ("date_format", |c| c.date_format = "%Q".to_string()),
+            ("permalink", |c| c.permalink = "blog/:slug".to_string()),
+            ("permalink", |c| c.permalink = "/blog/:year/".to_string()),
+            ("title_suffix", |c| c.title_suffix = " | site".to_string()),
+            ("favicon", |c| c.favicon.clear()),
+            ("blogroll[0].title", |c| {
+                c.blogroll = vec![BlogrollFeed {
+                    title: String::new(),
+                    xml_url: "https://other.com/rss.xml".to_string(),
+                    html_url: None,
+                }]
+            }),
+            ("blogroll[0].xml_url", |c| {
+                c.blogroll = vec![BlogrollFeed {
+                    title: "t".to_string(),
+                    xml_url: "/rss.xml".to_string(),
+                    html_url: None,
+                }]
+            }),
+            ("blogroll[0].html_url", |c| {
+                c.blogroll = vec![BlogrollFeed {
+                    title: "t".to_string(),
+                    xml_url: "https://other.com/rss.xml".to_string(),
+                    html_url: Some("other.com".to_string()),
+                }]
+            }),
+            ("head_hints.preconnect", |c| {
+                c.head_hints.preconnect = vec![HintOrigin {
+                    origin: "https://other.com/path".to_string(),
+                    crossorigin: false,
+                }]
+            }),
+            ("head_hints.dns_prefetch", |c| {
+                c.head_hints.dns_prefetch = vec!["other.com".to_string()]
+            }),
+        ];
+        for (field, break_field) in cases {
+            let mut config = valid();
+            break_field(&mut config);
+            let err = config.validate().unwrap_err().to_string();
+            assert!(err.contains(&format!("\n  {field}")), "{field}: {err}");
+        }
+
+        // the fine values of the same checks
+        let mut config = valid();
+        config.header.links = vec![link()];
+        config.footer.links = vec![link()];
+        config.blogroll = vec![feed()];
+        config.base_path = "/notes".to_string();
+        config.permalink = "/posts/:year/:slug".to_string();
+        config.title_suffix = "{title} | site".to_string();
+        config.anchor_min_level = 1;
+        config.anchor_max_level = 6;
+        config.validate().unwrap();
+
+        // the feed formats are checked when parsing
+        assert!(
+            serde_json::from_value::<Vec<FeedFormat>>(serde_json::json!(["rss", "rdf"])).is_err()
+        );
+    }
+}
@@ -1,39 +1,73 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, btree_map},
     fs,
     io::Write as _,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use anyhow::Context as _;
+use chrono::Datelike as _;
 
 use crate::{
     config::{self, Config},
     git_repo::{self, GitRepo},
-    markdown, pages,
+    images,
+    link_checker::LinkChecker,
+    markdown, pages, search_index, static_dir,
 };
 
+/// Stylesheets of the blog pages, see `static/.cssbundle`
+const BLOG_STYLESHEETS: &[&str] = &["/static/blog.css"];
+const BLOGROLL_OPML_FILE: &str = "blogroll.opml";
+const SEARCH_INDEX_FILE: &str = "search-index.json";
+const BUILD_INFO_FILE: &str = "build-info.json";
+const HUMANS_FILE: &str = "humans.txt";
+const REDIRECTS_FILE: &str = "_redirects";
+
 pub struct Generator {
     src_dir: PathBuf,
     dst_dir: PathBuf,
     config: Config,
     git_repo: GitRepo,
+    /// source path -> commits that modified it, newest first
+    commits: HashMap<PathBuf, Vec<BlogCommit>>,
     gitignore: ignore::gitignore::Gitignore,
+    skip: globset::GlobSet,
+    md_options: markdown::MarkdownOptions,
+    drafts: bool,
+    /// The posts published after this are in the future, see [`build_time`]
+    today: chrono::NaiveDate,
+    /// `None` if minify is disabled
+    minify_cfg: Option<minify_html::Cfg>,
+    partials: Partials,
 
     all_blog: Vec<BlogEntry>,
+    /// output html path -> source markdown path
+    html_sources: HashMap<PathBuf, PathBuf>,
+    /// `(alias, page rel path)` of the non-blog pages
+    page_aliases: Vec<(String, PathBuf)>,
+    /// logical -> fingerprinted urls of the static assets
+    assets: static_dir::Assets,
+    /// The feeds written and linked from the pages, known once the blog entries are read
+    feeds: Vec<config::FeedFormat>,
+    /// number of handled source files
+    file_count: usize,
 }
 
 #[derive(Debug, Clone)]
 struct BlogEntry {
     /// `blog/yyyy-mm-dd-blog-slug.md`
     rel_md_path: PathBuf,
-    /// `blog/yyyy-mm-dd-blog-slug`
+    /// Expanded from the `permalink` config, `blog/yyyy-mm-dd-blog-slug` by default,
+    /// or `blog/{slug}` if the frontmatter has `slug`
     rel_path: PathBuf,
 
     time: chrono::NaiveDate,
     slug: String,
 
-    last_commit: Option<BlogCommit>,
+    /// commits that modified the source file, newest first
+    commits: Vec<BlogCommit>,
 
     markdown: markdown::Markdown,
 }
@@ -43,90 +77,523 @@ pub struct BlogCommit {
     pub time: chrono::DateTime<chrono::FixedOffset>,
     pub hash: String,
     pub summary: Option<String>,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
     pub base_url: String,
 }
 
+/// Outputs depending on a changed source file, see [`Generator::update`]
+enum ChangeScope {
+    /// Not part of the site
+    Ignored,
+    /// Only its own output
+    File,
+    /// Other pages too
+    Site,
+}
+
+/// Summary of a finished build
+#[derive(Debug, Clone, Default)]
+pub struct BuildReport {
+    /// Number of html pages written, including the blog pages
+    pub pages: usize,
+    pub blog_posts: usize,
+    pub tags: usize,
+    /// Total size of the output files
+    pub bytes_written: u64,
+    pub elapsed: std::time::Duration,
+    /// All output files relative to the output dir, sorted
+    pub output_paths: Vec<PathBuf>,
+}
+
+/// Options overriding the site config for a single build
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    /// Also build the pages with `draft: true` in the frontmatter
+    pub drafts: bool,
+    /// Overrides `site_url` in the config
+    pub base_url: Option<String>,
+    /// Overrides `minify` in the config
+    pub minify: Option<bool>,
+    /// Overrides `git_concurrency` in the config
+    pub git_concurrency: Option<usize>,
+}
+
 impl Generator {
-    pub fn new(src_dir: impl Into<PathBuf>, dst_dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+    pub fn new(
+        src_dir: impl Into<PathBuf>,
+        dst_dir: impl Into<PathBuf>,
+        options: BuildOptions,
+    ) -> anyhow::Result<Self> {
         let src_dir = src_dir.into();
         let dst_dir = dst_dir.into();
 
-        if dst_dir.try_exists()? {
-            return Err(anyhow::anyhow!("output dir is not empty"));
+        let config_file = Config::find_file(&src_dir)?;
+        tracing::info!("read config from: {}", config_file.display());
+        let mut config = Config::from_file(src_dir.join(config_file))?;
+        if let Some(base_url) = options.base_url {
+            config.site_url = base_url;
         }
+        if let Some(minify) = options.minify {
+            config.minify = minify;
+        }
+        if let Some(limit) = options.git_concurrency {
+            config.git_concurrency = Some(limit);
+        }
+        config.validate()?;
+
+        tracing::info!("open git repo: {}", src_dir.display());
+        let git_concurrency = config
+            .git_concurrency
+            .unwrap_or_else(git_repo::default_concurrency);
+        let git_repo = GitRepo::with_concurrency(&src_dir, git_concurrency)?;
+
+        tracing::info!("read git history");
+        let commits = git_repo
+            .commits_by_path()?
+            .into_iter()
+            .map(|(path, commits)| {
+                let commits = commits
+                    .iter()
+                    .map(|c| BlogCommit::new(c, &config.commit_base_url))
+                    .collect();
+                (path, commits)
+            })
+            .collect();
 
-        log::info!("open git repo: {}", src_dir.display());
-        let git_repo = GitRepo::new(&src_dir)?;
+        tracing::info!("read gitignore");
+        let (gitignore, _err) = ignore::gitignore::Gitignore::new(src_dir.join(".gitignore"));
 
-        let config_file = Path::new("config.yaml");
-        log::info!("read config from: {}", config_file.display());
-        let config = Config::from_file(src_dir.join(config_file))?;
+        let mut skip = globset::GlobSetBuilder::new();
+        // the config file and the partials are not part of the site
+        skip.add(globset::Glob::new(&config_file.to_string_lossy())?);
+        skip.add(globset::Glob::new(config::PARTIALS_DIR)?);
+        for pattern in &config.skip {
+            let glob = globset::Glob::new(pattern)
+                .with_context(|| format!("invalid skip pattern: {pattern}"))?;
+            skip.add(glob);
+        }
+        if let Some(dir) = &config.syntaxes_dir {
+            skip.add(globset::Glob::new(&dir.to_string_lossy())?);
+        }
+        let skip = skip.build()?;
 
-        log::info!("read gitignore");
-        let (gitignore, _err) = ignore::gitignore::Gitignore::new(src_dir.join(".gitignore"));
+        let syntax_theme = config.syntax_theme.as_deref().filter(|theme| {
+            let found = markdown::has_syntax_theme(theme);
+            if !found {
+                tracing::warn!("syntax theme `{theme}` not found, fallback to CSS classes");
+            }
+            found
+        });
+        let syntaxes_dir = config.syntaxes_dir.as_ref().map(|dir| src_dir.join(dir));
+        let highlighter = markdown::Highlighter::new(
+            syntax_theme.filter(|_| !config.syntax_css),
+            syntaxes_dir.as_deref(),
+        )?;
+
+        let md_options = markdown::MarkdownOptions::from_config(&config, highlighter);
+
+        let minify_cfg = config.minify.then(|| minify_cfg(&config.minify_options));
+
+        let partials = Partials {
+            header: read_partial(&src_dir, "header.html")?,
+            footer: read_partial(&src_dir, "footer.html")?,
+        };
 
         Ok(Self {
             src_dir,
             dst_dir,
             config,
             git_repo,
+            commits,
             gitignore,
+            skip,
+            md_options,
+            drafts: options.drafts,
+            // the clock unless pinned, so the scheduled posts are published on the next build
+            today: build_time(chrono::Utc::now())?.date_naive(),
+            minify_cfg,
+            partials,
             all_blog: Vec::new(),
+            html_sources: HashMap::new(),
+            page_aliases: Vec::new(),
+            assets: static_dir::Assets::default(),
+            feeds: Vec::new(),
+            file_count: 0,
         })
     }
 
-    pub fn build(mut self) -> anyhow::Result<()> {
-        log::info!("create dest dir: {}", self.dst_dir.display());
+    pub fn build(mut self) -> anyhow::Result<BuildReport> {
+        let start = std::time::Instant::now();
+
+        if self.dst_dir.try_exists()? {
+            return Err(anyhow::anyhow!("output dir is not empty"));
+        }
+
+        tracing::info!("create dest dir: {}", self.dst_dir.display());
         fs::create_dir_all(&self.dst_dir)?;
 
-        log::info!("copy static dir: {}", config::STATIC_DIR);
-        crate::static_dir::copy_static_dir_to(self.dst_dir.join(config::STATIC_DIR))?;
+        self.write_static()?;
+
+        // render non-blog pages and collect blog entries, blog pages are
+        // rendered after all blog entries are collected, since they need
+        // the neighbors and related posts
+        let mut sources = Vec::new();
+        self.collect_sources(&self.src_dir, &mut sources)?;
+
+        // wikilinks can point to any page, so the page names are collected first
+        if self.config.markdown.get("wikilinks") == Some(&true) {
+            self.md_options.wikilinks = Arc::new(self.wikilink_targets(&sources)?);
+        }
 
-        let src_dir = self.src_dir.clone();
-        self.iter_dir(&src_dir)?;
+        // the blog entries first, the pages link the feeds only if there are posts
+        let (blog_sources, other_sources): (Vec<_>, Vec<_>) = sources
+            .iter()
+            .partition(|x| x.parent() == Some(Path::new(config::BLOG_DIR)));
+        for rel_path in blog_sources {
+            self.handle_file(rel_path)?;
+        }
+        self.feeds = self
+            .config
+            .feeds
+            .iter()
+            .copied()
+            .filter(|x| self.has_feed(*x))
+            .collect();
+        for rel_path in other_sources {
+            self.handle_file(rel_path)?;
+        }
 
         // handle special page
-        std::fs::copy(
-            self.dst_dir.join(Self::md_to_html_path(config::HOME_MD)),
-            self.dst_dir.join("index.html"),
-        )?;
+        let special_pages = &self.config.special_pages;
+        // a root `index.md` as the home page is already in place
+        let home = self.md_to_html_path(&special_pages.home);
+        if home != Path::new("index.html") {
+            if self.html_sources.contains_key(Path::new("index.html")) {
+                tracing::warn!("`index.md` is replaced by the home page, set it as the home page");
+            }
+            std::fs::copy(self.dst_dir.join(home), self.dst_dir.join("index.html"))?;
+        }
         std::fs::copy(
             self.dst_dir
-                .join(Self::md_to_html_path(config::NOT_FOUND_MD)),
-            self.dst_dir.join("not_found.html"),
+                .join(self.md_to_html_path(&special_pages.not_found)),
+            self.dst_dir.join(my_site_web::NOT_FOUND_PAGE_FILE),
+        )?;
+        if self.src_dir.join(&special_pages.error).exists() {
+            std::fs::copy(
+                self.dst_dir
+                    .join(self.md_to_html_path(&special_pages.error)),
+                self.dst_dir.join(my_site_web::ERROR_PAGE_FILE),
+            )?;
+        }
+
+        // settings of the web server, e.g. the trailing slash policy
+        let settings = my_site_web::ServerSettings {
+            trailing_slash: self.config.trailing_slash,
+            base_path: self.config.base_path.clone(),
+        };
+        fs::write(
+            self.dst_dir.join(my_site_web::SERVER_SETTINGS_FILE),
+            serde_json::to_vec(&settings)?,
         )?;
 
-        // process blog entries
-        self.all_blog.sort_by_key(|x| std::cmp::Reverse(x.time));
-        let all_blog_entries: Vec<_> = self.all_blog.iter().map(BlogEntry::as_page).collect();
+        // process blog entries, the blog pages are still built without any
+        // posts, and the blog dir may not exist in the source dir
+        fs::create_dir_all(self.dst_dir.join(config::BLOG_DIR))?;
+        self.check_duplicate_blog()?;
+        // sort by path for the same date, to make the output deterministic
+        self.all_blog.sort_by(|a, b| {
+            b.time
+                .cmp(&a.time)
+                .then_with(|| a.rel_path.cmp(&b.rel_path))
+        });
+        let all_blog_entries: Vec<_> = self
+            .all_blog
+            .iter()
+            .map(|x| x.as_page(&self.config))
+            .collect();
+
+        let series_list = self.process_series_list(&all_blog_entries);
 
-        log::info!("build blog home");
+        self.build_blog_pages(&all_blog_entries, &series_list)?;
+
+        tracing::info!("build blog home");
         self.build_blog_home(&all_blog_entries)?;
 
+        tracing::info!("build blog archive");
+        self.build_blog_archive(&all_blog_entries)?;
+
         let tag_blog_list = Self::process_tag_blog_list(&all_blog_entries);
 
+        // most used tags first, then alphabetical for a deterministic order
+        let mut tag_counts: Vec<_> = tag_blog_list
+            .iter()
+            .map(|(tag, entries)| (tag.as_str(), entries.len()))
+            .collect();
+        tag_counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+        tracing::info!("build blog tags index");
+        self.build_tags_index(&tag_counts)?;
+        let tag_count = tag_counts.len();
+
+        if self.config.stats_page {
+            tracing::info!("build blog stats");
+            self.build_stats(&tag_counts)?;
+        }
+
         for (tag, blog_entries) in tag_blog_list {
-            log::info!("build blog tag home: {tag}");
-            self.build_blog_tag_home(&tag, &blog_entries)?;
+            let has_feed = if self.config.feeds.contains(&config::FeedFormat::Rss) {
+                tracing::info!("build blog tag rss: {tag}");
+                self.build_tag_rss(&tag, &blog_entries)?
+            } else {
+                false
+            };
+
+            tracing::info!("build blog tag home: {tag}");
+            self.build_blog_tag_home(&tag, &blog_entries, has_feed)?;
+        }
+
+        for (series, blog_entries) in &series_list {
+            tracing::info!("build blog series home: {series}");
+            self.build_series_home(series, blog_entries)?;
+        }
+
+        for feed in &self.config.feeds {
+            if !self.feeds.contains(feed) {
+                tracing::info!("no feed items, skip {}", feed.path());
+                continue;
+            }
+            match feed {
+                config::FeedFormat::Rss => {
+                    tracing::info!("build rss");
+                    self.build_rss()?;
+                }
+                config::FeedFormat::Atom => {
+                    tracing::info!("build atom");
+                    self.build_atom()?;
+                }
+                config::FeedFormat::Json => {
+                    tracing::info!("build json feed");
+                    self.build_json_feed()?;
+                }
+            }
+        }
+
+        if !self.config.blogroll.is_empty() {
+            tracing::info!("build blogroll");
+            self.build_opml()?;
+            self.build_blogroll()?;
+        }
+
+        tracing::info!("build aliases");
+        self.build_aliases()?;
+
+        if self.config.search_index {
+            tracing::info!("build search index");
+            self.build_search_index()?;
+        }
+
+        if self.config.link_check != config::CheckLevel::Off {
+            tracing::info!("check links");
+            self.check_links()?;
+        }
+
+        if self.config.asset_check != config::CheckLevel::Off {
+            tracing::info!("check assets");
+            self.check_assets()?;
+        }
+
+        // after the checks, which resolve the urls in the output dir
+        if !self.config.base_path.is_empty() {
+            tracing::info!("prefix urls with base path: {}", self.config.base_path);
+            self.prefix_base_path()?;
+        }
+
+        let mut outputs = Vec::new();
+        collect_files(&self.dst_dir, &mut outputs)?;
+        outputs.sort();
+
+        let mut bytes_written = 0;
+        for path in &outputs {
+            bytes_written += fs::metadata(path)?.len();
+        }
+
+        let output_paths: Vec<_> = outputs
+            .iter()
+            .filter_map(|path| path.strip_prefix(&self.dst_dir).ok())
+            .map(Path::to_path_buf)
+            .collect();
+
+        let mut report = BuildReport {
+            pages: output_paths
+                .iter()
+                .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+                .count(),
+            blog_posts: self.all_blog.len(),
+            tags: tag_count,
+            bytes_written,
+            elapsed: start.elapsed(),
+            output_paths,
+        };
+
+        // written last to include the counts of the report
+        if self.config.build_info {
+            tracing::info!("build build info");
+            for path in self.build_info(&report)? {
+                report.bytes_written += fs::metadata(self.dst_dir.join(&path))?.len();
+                report.output_paths.push(path);
+            }
+            report.output_paths.sort();
+        }
+
+        tracing::info!(
+            files = self.file_count,
+            elapsed_ms = report.elapsed.as_millis(),
+            "build done"
+        );
+
+        Ok(report)
+    }
+
+    /// Update the output of a previous build for the `changed` source files, relative to
+    /// the source dir. Returns `false` if a change affects other pages (e.g. a blog post,
+    /// the config or a new page), the whole site has to be built again then.
+    pub fn update(mut self, changed: &[PathBuf]) -> anyhow::Result<bool> {
+        let mut files = Vec::new();
+        for rel_path in changed {
+            match self.change_scope(rel_path)? {
+                ChangeScope::Ignored => {}
+                ChangeScope::File => files.push(rel_path),
+                ChangeScope::Site => {
+                    tracing::info!("affects other pages: {}", rel_path.display());
+                    return Ok(false);
+                }
+            }
         }
+        if files.is_empty() {
+            return Ok(true);
+        }
+
+        // for the fingerprinted urls of the assets
+        self.write_static()?;
+        self.feeds = self
+            .config
+            .feeds
+            .iter()
+            .copied()
+            .filter(|x| self.dst_dir.join(x.path()).is_file())
+            .collect();
+
+        for rel_path in files {
+            self.handle_file(rel_path)?;
+
+            if rel_path.extension().and_then(|x| x.to_str()) != Some("md") {
+                continue;
+            }
+            // turned into a draft or with aliases to write
+            let html_path = self.md_to_html_path(rel_path);
+            if !self.html_sources.contains_key(&html_path) || !self.page_aliases.is_empty() {
+                tracing::info!("affects other pages: {}", rel_path.display());
+                return Ok(false);
+            }
+            if !self.config.base_path.is_empty() {
+                self.prefix_base_path_of(&self.dst_dir.join(html_path))?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Which outputs depend on the source file at `rel_path`
+    fn change_scope(&self, rel_path: &Path) -> anyhow::Result<ChangeScope> {
+        let src_path = self.src_dir.join(rel_path);
+        if self
+            .gitignore
+            .matched_path_or_any_parents(&src_path, false)
+            .is_ignore()
+        {
+            return Ok(ChangeScope::Ignored);
+        }
+
+        // the config, the partials and the syntaxes are skipped
+        let is_skipped = rel_path
+            .ancestors()
+            .any(|x| !x.as_os_str().is_empty() && self.skip.is_match(x));
+        // removed or renamed
+        if is_skipped || !src_path.is_file() {
+            return Ok(ChangeScope::Site);
+        }
+
+        if rel_path.extension().and_then(|x| x.to_str()) != Some("md") {
+            return Ok(ChangeScope::File);
+        }
+
+        // the blog pages, feeds and search index list the other pages,
+        // the wikilinks and the special pages depend on them
+        let special_pages = &self.config.special_pages;
+        let is_page = rel_path.parent() != Some(Path::new(config::BLOG_DIR))
+            && ![
+                &special_pages.home,
+                &special_pages.not_found,
+                &special_pages.error,
+            ]
+            .contains(&&rel_path.to_path_buf())
+            && !self.config.search_index
+            && self.config.markdown.get("wikilinks") != Some(&true)
+            // a new page, or a draft until now
+            && self.dst_dir.join(self.md_to_html_path(rel_path)).is_file();
+
+        Ok(if is_page {
+            ChangeScope::File
+        } else {
+            ChangeScope::Site
+        })
+    }
 
-        log::info!("build rss");
-        self.build_rss()?;
+    /// Copy the static dir, and build the syntax stylesheet if it's enabled
+    fn write_static(&mut self) -> anyhow::Result<()> {
+        tracing::info!("copy static dir: {}", config::STATIC_DIR);
+        self.assets = static_dir::copy_static_dir_to(self.dst_dir.join(config::STATIC_DIR))?;
+
+        if self.config.syntax_css {
+            let theme = self
+                .config
+                .syntax_theme
+                .as_deref()
+                .filter(|x| markdown::has_syntax_theme(x))
+                .unwrap_or(markdown::DEFAULT_SYNTAX_THEME);
+
+            tracing::info!("build syntax css: {theme}");
+            let css = markdown::syntax_theme_css(theme)?;
+            self.assets.write(
+                self.dst_dir.join(config::STATIC_DIR),
+                "syntax.css",
+                css.as_bytes(),
+            )?;
+        }
 
         Ok(())
     }
 
-    fn iter_dir(&mut self, rel_cur_dir: &Path) -> anyhow::Result<()> {
-        for entry in fs::read_dir(rel_cur_dir)? {
-            let entry = entry?;
-            let path = entry.path();
+    /// Source files to build relative to the source dir, in a stable order
+    fn collect_sources(
+        &self,
+        rel_cur_dir: &Path,
+        sources: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<()> {
+        // `read_dir` order depends on the file system, sort for a reproducible output
+        let mut paths = fs::read_dir(rel_cur_dir)?
+            .map(|x| x.map(|x| x.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        paths.sort();
 
+        for path in paths {
             let Ok(rel_path) = path.strip_prefix(&self.src_dir) else {
-                log::warn!("cannot get relative path for {}", path.display());
+                tracing::warn!("cannot get relative path for {}", path.display());
                 continue;
             };
 
-            if self.config.skip.contains(rel_path) {
+            if self.skip.is_match(rel_path) {
                 continue;
             }
 
@@ -137,16 +604,78 @@ impl Generator {
             }
 
             if is_dir {
-                self.iter_dir(&path)?;
+                self.collect_sources(&path, sources)?;
             } else {
-                self.handle_file(rel_path)?;
+                sources.push(rel_path.to_path_buf());
             }
         }
 
         Ok(())
     }
 
+    /// [`markdown::wikilink_key`] of the file names, slugs and titles of the pages -> page urls
+    fn wikilink_targets(&self, sources: &[PathBuf]) -> anyhow::Result<BTreeMap<String, String>> {
+        let mut targets = BTreeMap::new();
+        let url_style = self.config.url_style();
+
+        for rel_path in sources {
+            if rel_path.extension().and_then(|x| x.to_str()) != Some("md") {
+                continue;
+            }
+
+            let meta = markdown::read_meta(&self.src_dir, rel_path, &self.md_options)?;
+            if meta.draft && !self.drafts {
+                continue;
+            }
+
+            let p = rel_path.with_extension("");
+            let file_name = p.file_name().unwrap_or_default().to_string_lossy();
+            let mut names = vec![file_name.to_string(), meta.title];
+
+            let blog = p
+                .parent()
+                .filter(|x| *x == Path::new(config::BLOG_DIR))
+                .and_then(|_| markdown::parse_blog_file_name(&file_name).ok());
+            let url = if let Some((time, slug)) = blog {
+                if self.is_skipped_future(time) {
+                    continue;
+                }
+                let (slug, name) = blog_slug_name(&file_name, slug, meta.slug.as_deref());
+                let url = config::page_link(
+                    config::blog_permalink(&self.config.permalink, time, &slug, &name),
+                    url_style,
+                );
+                names.push(slug);
+                url
+            } else if *rel_path == self.config.special_pages.home {
+                "/".to_string()
+            } else {
+                config::page_link(&p, url_style)
+            };
+
+            for name in names {
+                match targets.entry(markdown::wikilink_key(&name)) {
+                    btree_map::Entry::Vacant(x) => {
+                        x.insert(url.clone());
+                    }
+                    btree_map::Entry::Occupied(x) if *x.get() != url => {
+                        tracing::warn!(
+                            "wikilink `[[{name}]]` is ambiguous, resolved to `{}` over `{url}`",
+                            x.get()
+                        );
+                    }
+                    btree_map::Entry::Occupied(_) => {}
+                }
+            }
+        }
+
+        Ok(targets)
+    }
+
+    #[tracing::instrument(skip_all, fields(path = %rel_path.display()))]
     fn handle_file(&mut self, rel_path: &Path) -> anyhow::Result<()> {
+        self.file_count += 1;
+
         let src_path = self.src_dir.join(rel_path);
         let dst_path = self.dst_dir.join(rel_path);
 
@@ -156,22 +685,64 @@ impl Generator {
 
         if rel_path.extension().and_then(|x| x.to_str()) == Some("md") {
             if let Some(blog_entry) = self.try_get_blog_entry(rel_path)? {
-                log::info!("build blog: {}", rel_path.display());
-                self.render_blog_page(&blog_entry, &blog_entry.rel_path)?;
+                if blog_entry.markdown.meta.draft && !self.drafts {
+                    tracing::info!("skip draft: {}", rel_path.display());
+                    return Ok(());
+                }
+                if blog_entry.time > self.today {
+                    match self.config.future_posts {
+                        config::FuturePosts::Skip => {
+                            tracing::info!("skip future post: {}", rel_path.display());
+                            return Ok(());
+                        }
+                        config::FuturePosts::Warn => {
+                            tracing::warn!("publish date is in the future: {}", rel_path.display())
+                        }
+                        config::FuturePosts::Publish => {}
+                    }
+                }
+
+                tracing::info!("read blog: {}", rel_path.display());
+                self.html_sources
+                    .insert(self.page_file(&blog_entry.rel_path), rel_path.to_path_buf());
                 self.all_blog.push(blog_entry);
             } else {
-                log::info!("build md: {}", rel_path.display());
-                let md = markdown::read_md(&self.src_dir, rel_path)?;
+                let md = markdown::read_md(&self.src_dir, rel_path, &self.md_options)?;
+                if md.meta.draft && !self.drafts {
+                    tracing::info!("skip draft: {}", rel_path.display());
+                    return Ok(());
+                }
+
+                tracing::info!("build md: {}", rel_path.display());
                 self.render_markdown(&md, rel_path)?;
+                self.html_sources
+                    .insert(self.md_to_html_path(rel_path), rel_path.to_path_buf());
+                for alias in md.meta.aliases {
+                    self.page_aliases.push((alias, rel_path.with_extension("")));
+                }
             }
         } else {
-            log::info!("copy file: {}", rel_path.display());
-            std::fs::copy(src_path, self.dst_dir.join(rel_path))?;
+            tracing::info!("copy file: {}", rel_path.display());
+            copy_file(&src_path, self.dst_dir.join(rel_path))?;
+
+            if !self.md_options.image_widths.is_empty() && images::is_resizable(rel_path) {
+                tracing::info!("resize image: {}", rel_path.display());
+                images::write_variants(
+                    &src_path,
+                    &self.dst_dir.join(rel_path),
+                    &self.md_options.image_widths,
+                )?;
+            }
         }
 
         Ok(())
     }
 
+    /// Whether a post published at `time` is left out by `future_posts`
+    fn is_skipped_future(&self, time: chrono::NaiveDate) -> bool {
+        time > self.today && self.config.future_posts == config::FuturePosts::Skip
+    }
+
     fn try_get_blog_entry(
         &self,
         rel_md_path: impl AsRef<Path>,
@@ -198,229 +769,1034 @@ impl Generator {
             return Ok(None);
         };
 
-        let commits = self.git_repo.commits_for_file(rel_md_path)?;
-        let last_commit = commits.first();
+        let commits = self.commits.get(rel_md_path).cloned().unwrap_or_default();
 
-        let markdown = markdown::read_md(&self.src_dir, rel_md_path)?;
+        let markdown = markdown::read_md(&self.src_dir, rel_md_path, &self.md_options)?;
+
+        // the date always comes from the file name
+        let (slug, name) = blog_slug_name(
+            &p.file_name().unwrap_or_default().to_string_lossy(),
+            slug,
+            markdown.meta.slug.as_deref(),
+        );
+        let rel_path = config::blog_permalink(&self.config.permalink, time, &slug, &name);
 
         Ok(Some(BlogEntry {
             rel_md_path: rel_md_path.to_path_buf(),
-            rel_path: rel_md_path.with_extension(""),
+            rel_path,
 
             time,
-            slug: slug.to_string(),
-            last_commit: last_commit.map(|c| BlogCommit {
-                time: git_repo::git_time_to_datetime(c.time()),
-                hash: c.id().to_string(),
-                summary: c.summary().map(|x| x.to_string()),
-                base_url: self.config.commit_base_url.clone(),
-            }),
+            slug,
+            commits,
 
             markdown,
         }))
     }
 
+    #[tracing::instrument(skip_all)]
     fn render_markdown(
         &'_ self,
         md: &markdown::Markdown,
         rel_path: impl AsRef<Path>,
     ) -> anyhow::Result<()> {
         let rel_path = rel_path.as_ref();
+        self.check_page_assets(rel_path, &md.meta);
 
-        let html_path = Self::md_to_html_path(rel_path);
+        let html_path = self.md_to_html_path(rel_path);
 
-        let title = if rel_path == Path::new(config::HOME_MD) {
-            &self.config.site_name
-        } else {
-            &self.title_with_author(&md.meta.title)
+        let title = &self.page_title(&md.meta.title, md.meta.title_suffix.as_deref());
+
+        let canonical = match &md.meta.canonical {
+            Some(canonical) => canonical.clone(),
+            None if rel_path == self.config.special_pages.home => self.site_url_of(""),
+            None => self.page_url_of(rel_path.with_extension("")),
         };
 
         let page = pages::Base {
             head: pages::Head {
-                title,
-                description: md.meta.description_md.as_deref(),
-                author: &self.config.author,
+                author: md.meta.author.as_deref().unwrap_or(&self.config.author),
+                lang: md.meta.lang.as_deref().unwrap_or(&self.config.lang),
+                translations: Some(&md.meta.translations),
+                has_math: md.has_math,
+                has_mermaid: md.has_mermaid,
+                theme_color: md
+                    .meta
+                    .theme_color
+                    .as_ref()
+                    .or(self.config.theme_color.as_ref()),
+                page_styles: &md.meta.styles,
+                page_scripts: &md.meta.scripts,
+                ..self.get_head(title, md.meta.description_md.as_deref(), &canonical)
             },
             body: pages::Body {
                 header: self.get_header(html_path.to_str()),
                 footer: self.get_footer(),
+                layout: md.meta.layout,
                 main: pages::Article { raw_html: &md.html },
             },
         };
 
         let output_path = self.dst_dir.join(&html_path);
-        page.render_into(output_path)
+        page.render_into(output_path, self.minify_cfg.as_ref())
             .context("failed to render page into file")?;
 
         Ok(())
     }
 
+    /// Blog entries with the same url would overwrite each other
+    fn check_duplicate_blog(&self) -> anyhow::Result<()> {
+        let mut seen = HashMap::new();
+
+        for blog in &self.all_blog {
+            if let Some(other) = seen.insert(&blog.rel_path, &blog.rel_md_path) {
+                return Err(anyhow::anyhow!(
+                    "blog `{}` and `{}` have the same slug: {}",
+                    other.display(),
+                    blog.rel_md_path.display(),
+                    blog.rel_path.display()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `blog_entries` should be `all_blog` as pages
+    fn build_blog_pages(
+        &self,
+        blog_entries: &[pages::BlogEntry],
+        series_list: &BTreeMap<String, Vec<pages::BlogEntry>>,
+    ) -> anyhow::Result<()> {
+        for (i, blog) in self.all_blog.iter().enumerate() {
+            tracing::info!("build blog: {}", blog.rel_md_path.display());
+            let related = self.related_blog(blog, blog_entries);
+
+            // sorted newest first
+            let newer = i.checked_sub(1).map(|i| blog_entries[i]);
+            let older = blog_entries.get(i + 1).copied();
+
+            let series = blog
+                .markdown
+                .meta
+                .series
+                .as_deref()
+                .and_then(|name| find_series(series_list, name))
+                .map(|(name, parts)| pages::Series {
+                    name,
+                    parts,
+                    current: &blog.rel_path,
+                    url_style: self.config.url_style(),
+                });
+
+            self.render_blog_page(blog, &related, newer, older, series)?;
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(path = %blog.rel_md_path.display()))]
     fn render_blog_page(
         &'_ self,
         blog: &BlogEntry,
-        rel_path: impl AsRef<Path>,
+        related: &[pages::BlogEntry<'_>],
+        newer: Option<pages::BlogEntry<'_>>,
+        older: Option<pages::BlogEntry<'_>>,
+        series: Option<pages::Series<'_>>,
     ) -> anyhow::Result<()> {
-        let html_path = Self::md_to_html_path(rel_path);
+        self.check_page_assets(&blog.rel_md_path, &blog.markdown.meta);
 
-        let title = self.title_with_author(&blog.markdown.meta.title);
+        let html_path = self.page_file(&blog.rel_path);
 
-        let last_update_time = blog.last_commit.as_ref().map(|x| x.time.date_naive());
+        let title = self.page_title(
+            &blog.markdown.meta.title,
+            blog.markdown.meta.title_suffix.as_deref(),
+        );
+
+        let last_update_time = blog.last_commit().map(|x| x.time.date_naive());
         let last_update_time = last_update_time.unwrap_or(blog.time);
 
+        let canonical = blog
+            .markdown
+            .meta
+            .canonical
+            .clone()
+            .unwrap_or_else(|| self.page_url_of(&blog.rel_path));
+
+        let comment_id = blog.comment_id();
+
+        // the title suffix still uses the site author
+        let author = blog
+            .markdown
+            .meta
+            .author
+            .as_deref()
+            .unwrap_or(&self.config.author);
+
+        let page =
+            pages::Base {
+                head: pages::Head {
+                    author,
+                    lang: blog
+                        .markdown
+                        .meta
+                        .lang
+                        .as_deref()
+                        .unwrap_or(&self.config.lang),
+                    translations: Some(&blog.markdown.meta.translations),
+                    has_math: blog.markdown.has_math,
+                    has_mermaid: blog.markdown.has_mermaid,
+                    theme_color: blog
+                        .markdown
+                        .meta
+                        .theme_color
+                        .as_ref()
+                        .or(self.config.theme_color.as_ref()),
+                    stylesheets: BLOG_STYLESHEETS,
+                    page_styles: &blog.markdown.meta.styles,
+                    page_scripts: &blog.markdown.meta.scripts,
+                    ..self.get_head(
+                        &title,
+                        blog.markdown.meta.description_md.as_deref(),
+                        &canonical,
+                    )
+                },
+                body: pages::Body {
+                    header: self.get_header(html_path.to_str()),
+                    footer: self.get_footer(),
+                    layout: blog.markdown.meta.layout,
+                    main: pages::BlogPage {
+                        author,
+                        publish_time: blog.time,
+                        last_update_time,
+                        last_commit: blog.last_commit(),
+                        contributors: if self.config.contributors {
+                            blog.contributors()
+                        } else {
+                            vec![]
+                        },
+                        commits: pages::Commits {
+                            commits: &blog.commits,
+                            max_commits: self.config.max_commits,
+                            history_url: self.config.history_base_url.as_ref().map(|base_url| {
+                                format!("{}/{}", base_url, blog.rel_md_path.display())
+                            }),
+                            date_format: &self.config.date_format,
+                        },
+                        markdown: &blog.markdown,
+                        related,
+                        newer,
+                        older,
+                        series,
+                        comments: self
+                            .config
+                            .comments
+                            .as_ref()
+                            .filter(|_| blog.markdown.meta.comments)
+                            .map(|config| pages::Comments {
+                                config,
+                                comment_id: &comment_id,
+                            }),
+                        url_style: self.config.url_style(),
+                        date_format: &self.config.date_format,
+                    },
+                },
+            };
+
+        let output_path = self.dst_dir.join(&html_path);
+        page.render_into(output_path, self.minify_cfg.as_ref())
+            .context("failed to render page into file")?;
+
+        Ok(())
+    }
+
+    fn build_blog_home(&self, blog_entries: &[pages::BlogEntry]) -> anyhow::Result<()> {
+        let html_path = self.page_file(config::BLOG_DIR);
+
+        let title = self.page_title("blog", None);
+
+        let canonical = self.page_url_of(config::BLOG_DIR);
+
         let page = pages::Base {
             head: pages::Head {
-                title: &title,
-                description: blog.markdown.meta.description_md.as_deref(),
-                author: &self.config.author,
+                stylesheets: BLOG_STYLESHEETS,
+                ..self.get_head(&title, Some("blog"), &canonical)
             },
             body: pages::Body {
                 header: self.get_header(html_path.to_str()),
                 footer: self.get_footer(),
-                main: pages::BlogPage {
-                    publish_time: blog.time,
-                    last_update_time,
-                    last_commit: blog.last_commit.as_ref(),
-                    markdown: &blog.markdown,
+                layout: config::Layout::Default,
+                main: pages::BlogHome {
+                    blog_entries,
+                    url_style: self.config.url_style(),
                 },
             },
         };
 
         let output_path = self.dst_dir.join(&html_path);
-        page.render_into(output_path)
+        page.render_into(output_path, self.minify_cfg.as_ref())
             .context("failed to render page into file")?;
 
         Ok(())
     }
 
-    fn build_blog_home(&self, blog_entries: &[pages::BlogEntry]) -> anyhow::Result<()> {
-        let html_path = "blog/index.html";
+    fn build_blog_archive(&self, blog_entries: &[pages::BlogEntry]) -> anyhow::Result<()> {
+        let html_path = self.page_file("blog/archive");
 
-        let title = self.title_with_author("blog");
+        let title = self.page_title("archive", None);
+        let canonical = self.page_url_of("blog/archive");
 
         let page = pages::Base {
             head: pages::Head {
-                title: &title,
-                description: Some("blog"),
-                author: &self.config.author,
+                stylesheets: BLOG_STYLESHEETS,
+                ..self.get_head(&title, Some("archive"), &canonical)
             },
             body: pages::Body {
-                header: self.get_header(Some(html_path)),
+                header: self.get_header(html_path.to_str()),
                 footer: self.get_footer(),
-                main: pages::BlogHome { blog_entries },
+                layout: config::Layout::Default,
+                main: pages::Archive { blog_entries },
             },
         };
 
-        let output_path = self.dst_dir.join(html_path);
-        page.render_into(output_path)
+        let output_path = self.dst_dir.join(&html_path);
+        page.render_into(output_path, self.minify_cfg.as_ref())
             .context("failed to render page into file")?;
 
         Ok(())
     }
 
-    fn build_blog_tag_home(
-        &self,
-        tag: &str,
-        blog_entries: &[pages::BlogEntry],
-    ) -> anyhow::Result<()> {
-        let html_path = format!("blog/tags/{}/index.html", tag);
+    fn build_tags_index(&self, tags: &[(&str, usize)]) -> anyhow::Result<()> {
+        let html_path = self.page_file("blog/tags");
 
-        let title = format!("#{tag}");
-        let title = self.title_with_author(&title);
+        let title = self.page_title("tags", None);
+        let canonical = self.page_url_of("blog/tags");
 
         let page = pages::Base {
             head: pages::Head {
-                title: &title,
-                description: Some(&title),
-                author: &self.config.author,
+                stylesheets: BLOG_STYLESHEETS,
+                ..self.get_head(&title, Some("tags"), &canonical)
             },
             body: pages::Body {
-                header: self.get_header(Some(&html_path)),
+                header: self.get_header(html_path.to_str()),
                 footer: self.get_footer(),
-                main: pages::BlogTagHome {
-                    tag_name: tag,
-                    blog_entries,
+                layout: config::Layout::Default,
+                main: pages::TagsIndex {
+                    tags,
+                    url_style: self.config.url_style(),
                 },
             },
         };
 
         let output_path = self.dst_dir.join(&html_path);
-        page.render_into(output_path)
+        page.render_into(output_path, self.minify_cfg.as_ref())
             .context("failed to render page into file")?;
 
         Ok(())
     }
 
-    fn build_rss(&self) -> anyhow::Result<()> {
-        let out_path = "blog/rss.xml";
-
-        let mut atom_link = rss::extension::atom::Link::default();
-        atom_link.set_href(format!("{}/{}", self.config.site_url, out_path));
-        atom_link.set_rel("self");
-        atom_link.set_mime_type(Some("application/rss+xml".to_string()));
-        let atom_ext = rss::extension::atom::AtomExtension {
-            links: vec![atom_link],
-        };
-
-        let last_update_time = self
-            .all_blog
-            .iter()
-            .filter_map(|x| x.last_commit.as_ref())
-            .map(|x| x.time.to_utc())
-            .max();
-
-        let Some(last_update_time) = last_update_time else {
-            return Ok(());
-        };
-
-        let items: Vec<_> = self.all_blog.iter().map(|x| self.to_rss_item(x)).collect();
+    /// Write the blogroll as [OPML 2.0](https://opml.org/spec2.opml)
+    fn build_opml(&self) -> anyhow::Result<()> {
+        let mut outlines = String::new();
+        for feed in &self.config.blogroll {
+            let html_url = feed
+                .html_url
+                .as_ref()
+                .map(|x| format!(" htmlUrl=\"{}\"", escape_xml(x)))
+                .unwrap_or_default();
+            outlines.push_str(&format!(
+                "    <outline type=\"rss\" text=\"{title}\" title=\"{title}\" xmlUrl=\"{}\"{html_url}/>\n",
+                escape_xml(&feed.xml_url),
+                title = escape_xml(&feed.title),
+            ));
+        }
 
-        let rss = rss::ChannelBuilder::default()
-            .title(&self.config.site_name)
-            .link(&self.config.site_url)
-            .description(&self.config.site_name)
-            .pub_date(last_update_time.to_rfc2822())
-            .last_build_date(last_update_time.to_rfc2822())
-            .items(items)
-            .atom_ext(atom_ext)
-            .build();
+        let title = escape_xml(&format!("Blogroll of {}", self.config.site_name));
+        let owner = escape_xml(&self.config.author);
+        let opml = [
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_string(),
+            "<opml version=\"2.0\">\n".to_string(),
+            format!("  <head>\n    <title>{title}</title>\n    <ownerName>{owner}</ownerName>\n  </head>\n"),
+            format!("  <body>\n{outlines}  </body>\n"),
+            "</opml>\n".to_string(),
+        ]
+        .concat();
 
-        fs::write(self.dst_dir.join(out_path), rss.to_string().into_bytes())?;
+        fs::write(self.dst_dir.join(BLOGROLL_OPML_FILE), opml)?;
 
         Ok(())
     }
 
-    fn process_tag_blog_list<'b>(
-        blog: &[pages::BlogEntry<'b>],
-    ) -> HashMap<String, Vec<pages::BlogEntry<'b>>> {
-        let mut ret: HashMap<_, Vec<_>> = HashMap::new();
+    fn build_blogroll(&self) -> anyhow::Result<()> {
+        let html_path = self.page_file("blogroll");
 
-        for &b in blog {
-            for t in b.tags {
-                match ret.get_mut(t) {
-                    Some(l) => {
-                        l.push(b);
-                    }
-                    None => {
-                        ret.insert(t.to_string(), vec![b]);
-                    }
-                }
-            }
-        }
+        let title = self.page_title("blogroll", None);
+        let canonical = self.page_url_of("blogroll");
 
-        ret
-    }
+        let page = pages::Base {
+            head: self.get_head(&title, Some("blogroll"), &canonical),
+            body: pages::Body {
+                header: self.get_header(html_path.to_str()),
+                footer: self.get_footer(),
+                layout: config::Layout::Default,
+                main: pages::Blogroll {
+                    feeds: &self.config.blogroll,
+                    opml_url: &format!("/{BLOGROLL_OPML_FILE}"),
+                },
+            },
+        };
 
-    /// `abc.md` -> `abc/index.html`
-    /// `/aaa/abc.md` -> `/aaa/abc/index.html`
-    fn md_to_html_path(md: impl AsRef<Path>) -> PathBuf {
-        md.as_ref().with_extension("").join("index.html")
+        let output_path = self.dst_dir.join(&html_path);
+        page.render_into(output_path, self.minify_cfg.as_ref())
+            .context("failed to render page into file")?;
+
+        Ok(())
     }
 
-    fn to_rss_item(&self, blog_entry: &BlogEntry) -> rss::Item {
-        let link = format!("{}/{}", self.config.site_url, blog_entry.rel_path.display());
-        let author = format!("{} ({})", self.config.author_email, self.config.author);
+    /// `tags` is `(tag, post count)` sorted by post count
+    fn build_stats(&self, tags: &[(&str, usize)]) -> anyhow::Result<()> {
+        let html_path = self.page_file("blog/stats");
 
-        let description = blog_entry.markdown.meta.description_html.clone();
+        let title = self.page_title("stats", None);
+        let canonical = self.page_url_of("blog/stats");
+
+        let mut years: BTreeMap<i32, usize> = BTreeMap::new();
+        for blog in &self.all_blog {
+            *years.entry(blog.time.year()).or_default() += 1;
+        }
+        let years: Vec<_> = years.into_iter().rev().collect();
+
+        let page = pages::Base {
+            head: pages::Head {
+                stylesheets: BLOG_STYLESHEETS,
+                ..self.get_head(&title, Some("stats"), &canonical)
+            },
+            body: pages::Body {
+                header: self.get_header(html_path.to_str()),
+                footer: self.get_footer(),
+                layout: config::Layout::Default,
+                main: pages::Stats {
+                    total_posts: self.all_blog.len(),
+                    total_words: self
+                        .all_blog
+                        .iter()
+                        .map(|x| x.markdown.meta.word_count)
+                        .sum(),
+                    tags,
+                    years: &years,
+                    url_style: self.config.url_style(),
+                },
+            },
+        };
+
+        let output_path = self.dst_dir.join(&html_path);
+        page.render_into(output_path, self.minify_cfg.as_ref())
+            .context("failed to render page into file")?;
+
+        Ok(())
+    }
+
+    fn build_blog_tag_home(
+        &self,
+        tag: &str,
+        blog_entries: &[pages::BlogEntry],
+        has_feed: bool,
+    ) -> anyhow::Result<()> {
+        let html_path = self.page_file(format!("blog/tags/{}", config::slugify_tag(tag)));
+
+        let title = format!("#{tag}");
+        let title = self.page_title(&title, None);
+
+        let canonical = format!(
+            "{}{}",
+            self.site_root(),
+            config::tag_to_link(tag, self.config.url_style())
+        );
+        let feed_url = format!("/{}", config::tag_feed_path(tag));
+
+        let page = pages::Base {
+            head: pages::Head {
+                stylesheets: BLOG_STYLESHEETS,
+                tag_feed: has_feed.then_some(feed_url.as_str()),
+                ..self.get_head(&title, Some(&title), &canonical)
+            },
+            body: pages::Body {
+                header: self.get_header(html_path.to_str()),
+                footer: self.get_footer(),
+                layout: config::Layout::Default,
+                main: pages::BlogTagHome {
+                    tag_name: tag,
+                    blog_entries,
+                },
+            },
+        };
+
+        let output_path = self.dst_dir.join(&html_path);
+        page.render_into(output_path, self.minify_cfg.as_ref())
+            .context("failed to render page into file")?;
+
+        Ok(())
+    }
+
+    fn build_series_home(
+        &self,
+        series: &str,
+        blog_entries: &[pages::BlogEntry],
+    ) -> anyhow::Result<()> {
+        let html_path = self.page_file(format!("blog/series/{}", config::slugify_tag(series)));
+
+        let title = format!("series: {series}");
+        let title = self.page_title(&title, None);
+
+        let canonical = format!(
+            "{}{}",
+            self.site_root(),
+            config::series_to_link(series, self.config.url_style())
+        );
+
+        let page = pages::Base {
+            head: pages::Head {
+                stylesheets: BLOG_STYLESHEETS,
+                ..self.get_head(&title, Some(&title), &canonical)
+            },
+            body: pages::Body {
+                header: self.get_header(html_path.to_str()),
+                footer: self.get_footer(),
+                layout: config::Layout::Default,
+                main: pages::SeriesHome {
+                    name: series,
+                    blog_entries,
+                },
+            },
+        };
+
+        let output_path = self.dst_dir.join(&html_path);
+        page.render_into(output_path, self.minify_cfg.as_ref())
+            .context("failed to render page into file")?;
+
+        Ok(())
+    }
+
+    /// RSS and Atom without items are not valid feeds, and RSS is
+    /// dated by the commits, see [`Self::write_rss`]
+    fn has_feed(&self, feed: config::FeedFormat) -> bool {
+        match feed {
+            config::FeedFormat::Rss => self.all_blog.iter().any(|x| x.last_commit().is_some()),
+            config::FeedFormat::Atom => !self.all_blog.is_empty(),
+            config::FeedFormat::Json => true,
+        }
+    }
+
+    fn build_rss(&self) -> anyhow::Result<()> {
+        let entries: Vec<_> = self.all_blog.iter().collect();
+        self.write_rss(
+            &entries,
+            config::FeedFormat::Rss.path(),
+            &self.config.site_name,
+            &self.site_url_of(""),
+        )?;
+
+        Ok(())
+    }
+
+    /// RSS of a tag at `blog/tags/{tag}/rss.xml`, returns if it's written
+    fn build_tag_rss(&self, tag: &str, blog_entries: &[pages::BlogEntry]) -> anyhow::Result<bool> {
+        let entries: Vec<_> = self
+            .all_blog
+            .iter()
+            .filter(|x| blog_entries.iter().any(|e| e.rel_path == x.rel_path))
+            .collect();
+
+        self.write_rss(
+            &entries,
+            &config::tag_feed_path(tag),
+            &format!("#{tag} - {}", self.config.site_name),
+            &format!(
+                "{}{}",
+                self.site_root(),
+                config::tag_to_link(tag, self.config.url_style())
+            ),
+        )
+    }
+
+    /// Write RSS of `entries` (newest first) to `out_path`, nothing is written
+    /// and returns `false` if none of the entries are committed yet
+    fn write_rss(
+        &self,
+        entries: &[&BlogEntry],
+        out_path: &str,
+        title: &str,
+        link: &str,
+    ) -> anyhow::Result<bool> {
+        let mut atom_link = rss::extension::atom::Link::default();
+        atom_link.set_href(self.site_url_of(out_path));
+        atom_link.set_rel("self");
+        atom_link.set_mime_type(Some(config::FeedFormat::Rss.mime_type().to_string()));
+        let atom_ext = rss::extension::atom::AtomExtension {
+            links: vec![atom_link],
+        };
+
+        let last_update_time = entries
+            .iter()
+            .filter_map(|x| x.last_commit())
+            .map(|x| x.time.to_utc())
+            .max();
+
+        let Some(last_update_time) = last_update_time else {
+            return Ok(false);
+        };
+        let last_update_time = build_time(last_update_time)?;
+
+        let max_items = match self.config.rss_max_items {
+            Some(0) | None => usize::MAX,
+            Some(n) => n,
+        };
+        let items: Vec<_> = entries
+            .iter()
+            .take(max_items)
+            .map(|x| self.to_rss_item(x))
+            .collect::<anyhow::Result<_>>()?;
+
+        let rss = rss::ChannelBuilder::default()
+            .title(title)
+            .link(link)
+            .description(title)
+            .language(Some(self.config.lang.clone()))
+            .pub_date(last_update_time.to_rfc2822())
+            .last_build_date(last_update_time.to_rfc2822())
+            .items(items)
+            .atom_ext(atom_ext)
+            .build();
+
+        let path = self.dst_dir.join(out_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, rss.to_string().into_bytes())?;
+
+        Ok(true)
+    }
+
+    /// Build [Atom 1.0](https://www.rfc-editor.org/rfc/rfc4287)
+    fn build_atom(&self) -> anyhow::Result<()> {
+        let out_path = config::FeedFormat::Atom.path();
+
+        let entries: Vec<_> = self
+            .all_blog
+            .iter()
+            .map(|x| self.to_atom_entry(x))
+            .collect::<anyhow::Result<_>>()?;
+
+        let updated = entries
+            .iter()
+            .map(|x| x.updated)
+            .max()
+            .context("no blog posts for atom")?;
+        let updated = build_time(updated.to_utc())?.fixed_offset();
+
+        // the date of the oldest post, when the site owned the domain for sure
+        let feed_date = self
+            .all_blog
+            .iter()
+            .map(|x| x.time)
+            .min()
+            .unwrap_or_default();
+
+        let feed = atom_syndication::FeedBuilder::default()
+            .title(self.config.site_name.as_str())
+            .id(self.tag_uri(feed_date, config::BLOG_DIR))
+            .updated(updated)
+            .authors(vec![atom_syndication::Person {
+                name: self.config.author.clone(),
+                email: Some(self.config.author_email.clone()),
+                uri: Some(self.config.site_url.clone()),
+            }])
+            .links(vec![
+                atom_syndication::LinkBuilder::default()
+                    .href(self.site_url_of(out_path))
+                    .rel("self")
+                    .mime_type(Some(config::FeedFormat::Atom.mime_type().to_string()))
+                    .build(),
+                atom_syndication::LinkBuilder::default()
+                    .href(self.page_url_of(config::BLOG_DIR))
+                    .rel("alternate")
+                    .build(),
+            ])
+            .entries(entries)
+            .build();
+
+        fs::write(self.dst_dir.join(out_path), feed.to_string().into_bytes())?;
+
+        Ok(())
+    }
+
+    /// Write a redirect page at each alias of the pages, and the `_redirects` file if enabled
+    fn build_aliases(&self) -> anyhow::Result<()> {
+        let blog_aliases = self.all_blog.iter().flat_map(|blog| {
+            blog.markdown
+                .meta
+                .aliases
+                .iter()
+                .map(|alias| (alias, blog.rel_path.as_path()))
+        });
+        let page_aliases = self
+            .page_aliases
+            .iter()
+            .map(|(alias, rel_path)| (alias, rel_path.as_path()));
+
+        let aliases: Vec<_> = page_aliases
+            .chain(blog_aliases)
+            .map(|(alias, rel_path)| (alias, rel_path, self.page_file(alias.trim_matches('/'))))
+            .collect();
+
+        // check all the aliases before writing any, so that a collision does not
+        // depend on the build order
+        let later_outputs = self.outputs_after_aliases();
+        let mut alias_files: HashMap<&Path, &str> = HashMap::new();
+        for (alias, rel_path, html_path) in &aliases {
+            let collision = if self.dst_dir.join(html_path).try_exists()? {
+                let source = self.html_sources.get(html_path).unwrap_or(html_path);
+                Some(source.display().to_string())
+            } else if let Some(file) = later_outputs
+                .iter()
+                .find(|file| html_path.starts_with(file) || Path::new(file).starts_with(html_path))
+            {
+                Some(file.to_string())
+            } else {
+                alias_files
+                    .insert(html_path, alias)
+                    .map(|other| format!("alias `{other}`"))
+            };
+
+            if let Some(collision) = collision {
+                return Err(anyhow::anyhow!(
+                    "alias `{alias}` of {} collides with {collision}",
+                    rel_path.display(),
+                ));
+            }
+        }
+
+        let mut redirects = String::new();
+
+        for (alias, rel_path, html_path) in aliases {
+            let output_path = self.dst_dir.join(&html_path);
+
+            tracing::info!("build alias: {alias} -> {}", rel_path.display());
+            let page = pages::Redirect {
+                url: &self.page_url_of(rel_path),
+            };
+            page.render_into(output_path, self.minify_cfg.as_ref())
+                .context("failed to render page into file")?;
+
+            redirects.push_str(&format!(
+                "{}{} {}{} 301\n",
+                self.config.base_path,
+                alias,
+                self.config.base_path,
+                config::page_link(rel_path, self.config.url_style())
+            ));
+        }
+
+        if self.config.redirects_file {
+            fs::write(self.dst_dir.join(REDIRECTS_FILE), redirects)?;
+        }
+
+        Ok(())
+    }
+
+    /// The files written after [`Self::build_aliases`], relative to the output dir
+    fn outputs_after_aliases(&self) -> Vec<&'static str> {
+        let mut outputs = vec![BUILD_INFO_FILE, HUMANS_FILE];
+        if self.config.search_index {
+            outputs.push(SEARCH_INDEX_FILE);
+        }
+        if self.config.redirects_file {
+            outputs.push(REDIRECTS_FILE);
+        }
+        outputs
+    }
+
+    /// Write `build-info.json`, and `humans.txt` if the source dir has none,
+    /// returns the written paths relative to the output dir.
+    ///
+    /// The build time is the time of the `HEAD` commit, so that rebuilding the same
+    /// source gives the same output, see [`build_time`].
+    fn build_info(&self, report: &BuildReport) -> anyhow::Result<Vec<PathBuf>> {
+        let now = build_time(self.git_repo.head_time()?.to_utc())?;
+        let version = env!("CARGO_PKG_VERSION");
+
+        let info = serde_json::json!({
+            "commit": self.git_repo.head_hash()?,
+            "built_at": now.to_rfc3339(),
+            "generator_version": version,
+            "pages": report.pages,
+            "blog_posts": report.blog_posts,
+        });
+        let mut written = vec![PathBuf::from(BUILD_INFO_FILE)];
+        fs::write(
+            self.dst_dir.join(BUILD_INFO_FILE),
+            serde_json::to_vec_pretty(&info)?,
+        )?;
+
+        let humans_path = self.dst_dir.join(HUMANS_FILE);
+        if !humans_path.try_exists()? {
+            let humans = format!(
+                "/* TEAM */\nAuthor: {}\nContact: {}\n\n/* SITE */\nLast update: {}\nSoftware: my-site-generator {version}\n",
+                self.config.author,
+                self.config.author_email,
+                now.format("%Y/%m/%d"),
+            );
+            fs::write(humans_path, humans)?;
+            written.push(PathBuf::from(HUMANS_FILE));
+        }
+
+        Ok(written)
+    }
+
+    /// Build [JSON Feed 1.1](https://www.jsonfeed.org/version/1.1/)
+    fn build_json_feed(&self) -> anyhow::Result<()> {
+        let out_path = config::FeedFormat::Json.path();
+
+        let items: Vec<_> = self
+            .all_blog
+            .iter()
+            .map(|x| self.to_json_feed_item(x))
+            .collect::<anyhow::Result<_>>()?;
+
+        let feed = serde_json::json!({
+            "version": "https://jsonfeed.org/version/1.1",
+            "title": self.config.site_name,
+            "home_page_url": self.site_url_of(""),
+            "feed_url": self.site_url_of(out_path),
+            "language": self.config.lang,
+            "items": items,
+        });
+
+        fs::write(self.dst_dir.join(out_path), serde_json::to_vec(&feed)?)?;
+
+        Ok(())
+    }
+
+    fn check_links(&self) -> anyhow::Result<()> {
+        let checker = LinkChecker::new(&self.dst_dir, self.config.link_check_anchors);
+        let broken_links = checker.check()?;
+
+        for link in &broken_links {
+            let source = self.html_sources.get(&link.page).unwrap_or(&link.page);
+            tracing::warn!("broken link in {}: {}", source.display(), link.target);
+        }
+
+        if self.config.link_check == config::CheckLevel::Error && !broken_links.is_empty() {
+            return Err(anyhow::anyhow!("found {} broken links", broken_links.len()));
+        }
+
+        Ok(())
+    }
+
+    /// Prepend `base_path` to the absolute internal urls (e.g. `/blog/abc/`) of all the pages
+    fn prefix_base_path(&self) -> anyhow::Result<()> {
+        let mut files = Vec::new();
+        collect_files(&self.dst_dir, &mut files)?;
+
+        for path in files {
+            if path.extension().and_then(|x| x.to_str()) == Some("html") {
+                self.prefix_base_path_of(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn prefix_base_path_of(&self, path: &Path) -> anyhow::Result<()> {
+        let html = fs::read_to_string(path)?;
+        let html = markdown::prefix_urls(&html, &self.config.base_path)
+            .with_context(|| format!("failed to prefix urls: {}", path.display()))?;
+        fs::write(path, html)?;
+
+        Ok(())
+    }
+
+    fn check_assets(&self) -> anyhow::Result<()> {
+        let checker = LinkChecker::new(&self.dst_dir, false);
+
+        // only the pages from markdown, sorted to report in a stable order
+        let mut pages: Vec<_> = self.html_sources.keys().map(PathBuf::as_path).collect();
+        pages.sort();
+        let missing = checker.check_assets(pages)?;
+
+        for asset in &missing {
+            let source = self.html_sources.get(&asset.page).unwrap_or(&asset.page);
+            tracing::warn!("missing asset in {}: {}", source.display(), asset.target);
+        }
+
+        if self.config.asset_check == config::CheckLevel::Error && !missing.is_empty() {
+            return Err(anyhow::anyhow!("found {} missing assets", missing.len()));
+        }
+
+        Ok(())
+    }
+
+    fn build_search_index(&self) -> anyhow::Result<()> {
+        let out_path = SEARCH_INDEX_FILE;
+
+        let entries: Vec<_> = self
+            .all_blog
+            .iter()
+            .map(|x| search_index::SearchEntry {
+                title: &x.markdown.meta.title,
+                url: format!(
+                    "{}{}",
+                    self.config.base_path,
+                    config::page_link(&x.rel_path, self.config.url_style())
+                ),
+                tags: &x.markdown.meta.tags,
+                body: search_index::html_to_text(&x.markdown.html),
+            })
+            .collect();
+
+        fs::write(self.dst_dir.join(out_path), serde_json::to_vec(&entries)?)?;
+
+        Ok(())
+    }
+
+    /// Series name -> parts of the series sorted by publish time
+    ///
+    /// Series with the same slug are merged under the first one in the posts order,
+    /// find the series of a post with [`find_series`].
+    ///
+    /// `blog_entries` should be `all_blog` as pages
+    fn process_series_list<'b>(
+        &self,
+        blog_entries: &[pages::BlogEntry<'b>],
+    ) -> BTreeMap<String, Vec<pages::BlogEntry<'b>>> {
+        let mut ret: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        // slug -> series
+        let mut names: HashMap<String, String> = HashMap::new();
+
+        for (blog, &entry) in self.all_blog.iter().zip(blog_entries) {
+            let Some(series) = &blog.markdown.meta.series else {
+                continue;
+            };
+
+            let slug = config::slugify_tag(series);
+            if slug.is_empty() {
+                tracing::warn!("series `{series}` has no url safe characters, skip");
+                continue;
+            }
+
+            let name = names.entry(slug).or_insert_with(|| series.clone());
+            if name != series {
+                tracing::warn!("series `{series}` has the same url as `{name}`, merged");
+            }
+            ret.entry(name.clone()).or_default().push(entry);
+        }
+
+        // `blog_entries` is sorted newest first
+        for parts in ret.values_mut() {
+            parts.reverse();
+        }
+
+        ret
+    }
+
+    /// Tags are hierarchical with `/`, posts of `rust/async` are also listed under `rust`.
+    ///
+    /// Tags with the same slug are merged under the first one in the posts order.
+    fn process_tag_blog_list<'b>(
+        blog: &[pages::BlogEntry<'b>],
+    ) -> BTreeMap<String, Vec<pages::BlogEntry<'b>>> {
+        let mut ret: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        // slug -> tag
+        let mut names: HashMap<String, String> = HashMap::new();
+
+        for &b in blog {
+            // a post tagged both `rust` and `rust/async` is listed once under `rust`
+            let mut tags = BTreeSet::new();
+            for t in b.tags.iter().flat_map(|t| config::tag_ancestors(t)) {
+                let slug = config::slugify_tag(&t);
+                if slug.is_empty() {
+                    tracing::warn!("tag `{t}` has no url safe characters, skip");
+                    continue;
+                }
+
+                let name = names.entry(slug).or_insert_with(|| t.clone());
+                if *name != t {
+                    tracing::warn!("tag `{t}` has the same url as `{name}`, merged");
+                }
+                tags.insert(name.clone());
+            }
+
+            for t in tags {
+                match ret.get_mut(&t) {
+                    Some(l) => {
+                        l.push(b);
+                    }
+                    None => {
+                        ret.insert(t, vec![b]);
+                    }
+                }
+            }
+        }
+
+        ret
+    }
+
+    /// Blog entries sharing the most tags with `blog`, ties broken by recency.
+    fn related_blog<'b>(
+        &self,
+        blog: &BlogEntry,
+        blog_entries: &[pages::BlogEntry<'b>],
+    ) -> Vec<pages::BlogEntry<'b>> {
+        let tags = &blog.markdown.meta.tags;
+
+        let mut related: Vec<_> = blog_entries
+            .iter()
+            .filter(|x| x.rel_path != blog.rel_path.as_path())
+            .map(|&x| (x.tags.iter().filter(|t| tags.contains(t)).count(), x))
+            .filter(|(shared, _)| *shared > 0)
+            .collect();
+
+        // `blog_entries` is sorted newest first, and the sort is stable
+        related.sort_by_key(|(shared, _)| std::cmp::Reverse(*shared));
+
+        related
+            .into_iter()
+            .take(self.config.related_posts)
+            .map(|(_, x)| x)
+            .collect()
+    }
+
+    /// `abc.md` -> `abc/index.html`
+    /// `/aaa/abc.md` -> `/aaa/abc/index.html`
+    ///
+    /// or `abc.md` -> `abc.html` without pretty urls
+    fn md_to_html_path(&self, md: impl AsRef<Path>) -> PathBuf {
+        self.page_file(md.as_ref().with_extension(""))
+    }
+
+    fn page_file(&self, rel_path: impl AsRef<Path>) -> PathBuf {
+        config::page_file(rel_path, self.config.pretty_urls)
+    }
+
+    fn to_rss_item(&self, blog_entry: &BlogEntry) -> anyhow::Result<rss::Item> {
+        let link = self.page_url_of(&blog_entry.rel_path);
+        // the email of guest authors is unknown
+        let author = match &blog_entry.markdown.meta.author {
+            Some(author) => author.clone(),
+            None => format!("{} ({})", self.config.author_email, self.config.author),
+        };
+
+        let description = blog_entry
+            .markdown
+            .meta
+            .description_html
+            .as_deref()
+            .map(|x| self.feed_html(blog_entry, x))
+            .transpose()?;
+        let content = if self.config.rss_full_content {
+            Some(self.feed_html(blog_entry, &blog_entry.markdown.html)?)
+        } else {
+            None
+        };
 
         let categories: Vec<_> = blog_entry
             .markdown
@@ -433,13 +1809,9 @@ impl Generator {
             })
             .collect();
 
-        let pub_date = blog_entry
-            .time
-            .and_time(chrono::NaiveTime::from_hms_opt(0, 0, 0).unwrap())
-            .and_utc()
-            .to_rfc2822();
+        let pub_date = blog_entry.publish_datetime().to_rfc2822();
 
-        rss::ItemBuilder::default()
+        let item = rss::ItemBuilder::default()
             .title(blog_entry.markdown.meta.title.clone())
             .link(Some(link.clone()))
             .description(description)
@@ -450,12 +1822,211 @@ impl Generator {
                 permalink: true,
             }))
             .pub_date(Some(pub_date))
-            .content(blog_entry.markdown.html.clone())
-            .build()
+            .content(content)
+            .build();
+
+        Ok(item)
+    }
+
+    fn to_atom_entry(&self, blog_entry: &BlogEntry) -> anyhow::Result<atom_syndication::Entry> {
+        let link = self.page_url_of(&blog_entry.rel_path);
+
+        // the email of guest authors is unknown
+        let author = match &blog_entry.markdown.meta.author {
+            Some(author) => atom_syndication::Person {
+                name: author.clone(),
+                email: None,
+                uri: None,
+            },
+            None => atom_syndication::Person {
+                name: self.config.author.clone(),
+                email: Some(self.config.author_email.clone()),
+                uri: Some(self.config.site_url.clone()),
+            },
+        };
+
+        let published = blog_entry.publish_datetime().fixed_offset();
+        let updated = blog_entry
+            .last_commit()
+            .map(|x| x.time)
+            .unwrap_or(published);
+
+        let categories: Vec<_> = blog_entry
+            .markdown
+            .meta
+            .tags
+            .iter()
+            .map(|x| {
+                atom_syndication::CategoryBuilder::default()
+                    .term(x.clone())
+                    .build()
+            })
+            .collect();
+
+        let summary = blog_entry
+            .markdown
+            .meta
+            .description_html
+            .as_deref()
+            .map(|x| self.feed_html(blog_entry, x))
+            .transpose()?;
+        let content = self.feed_html(blog_entry, &blog_entry.markdown.html)?;
+
+        let entry = atom_syndication::EntryBuilder::default()
+            .title(blog_entry.markdown.meta.title.as_str())
+            .id(self.tag_uri(blog_entry.time, &blog_entry.rel_path))
+            .updated(updated)
+            .published(Some(published))
+            .authors(vec![author])
+            .links(vec![
+                atom_syndication::LinkBuilder::default()
+                    .href(link)
+                    .rel("alternate")
+                    .build(),
+            ])
+            .categories(categories)
+            .summary(summary.map(atom_syndication::Text::html))
+            .content(Some(
+                atom_syndication::ContentBuilder::default()
+                    .value(Some(content))
+                    .content_type(Some("html".to_string()))
+                    .build(),
+            ))
+            .build();
+
+        Ok(entry)
+    }
+
+    /// [Tag URI](https://www.rfc-editor.org/rfc/rfc4151) of the path,
+    /// e.g. `tag:example.com,2025-01-02:blog/abc`
+    fn tag_uri(&self, date: chrono::NaiveDate, rel_path: impl AsRef<Path>) -> String {
+        let authority = self
+            .config
+            .site_url
+            .split_once("://")
+            .map_or(self.config.site_url.as_str(), |(_, rest)| rest)
+            .split(['/', ':'])
+            .next()
+            .unwrap_or_default();
+
+        format!(
+            "tag:{},{}:{}",
+            authority,
+            date.format("%Y-%m-%d"),
+            rel_path.as_ref().display()
+        )
+    }
+
+    fn to_json_feed_item(&self, blog_entry: &BlogEntry) -> anyhow::Result<serde_json::Value> {
+        let link = self.page_url_of(&blog_entry.rel_path);
+        let content = self.feed_html(blog_entry, &blog_entry.markdown.html)?;
+
+        Ok(serde_json::json!({
+            "id": link,
+            "url": link,
+            "title": blog_entry.markdown.meta.title,
+            "content_html": content,
+            "date_published": blog_entry.publish_datetime().to_rfc3339(),
+            "tags": blog_entry.markdown.meta.tags,
+            "language": blog_entry.markdown.meta.lang.as_deref().unwrap_or(&self.config.lang),
+        }))
+    }
+
+    /// `html` of the blog post with absolute urls, since feed readers show it out of the page
+    fn feed_html(&self, blog_entry: &BlogEntry, html: &str) -> anyhow::Result<String> {
+        // the page is served at `/blog/abc/` with pretty urls
+        let mut page_path = config::page_link(&blog_entry.rel_path, self.config.url_style());
+        if self.config.pretty_urls && !page_path.ends_with('/') {
+            page_path.push('/');
+        }
+
+        markdown::absolute_urls(html, &self.site_root(), &page_path)
+            .context("failed to rewrite urls of feed content")
+    }
+
+    /// `{site_url}{base_path}`, without the trailing slash
+    fn site_root(&self) -> String {
+        format!("{}{}", self.config.site_url, self.config.base_path)
     }
 
-    fn title_with_author(&self, title: &str) -> String {
-        format!("{} - {}", title, self.config.author)
+    /// `blog/rss.xml` -> `{site_url}{base_path}/blog/rss.xml`
+    fn site_url_of(&self, rel_path: impl AsRef<Path>) -> String {
+        format!("{}/{}", self.site_root(), rel_path.as_ref().display())
+    }
+
+    /// `blog/abc` -> `{site_url}{base_path}/blog/abc`, or `.../blog/abc.html` without pretty urls
+    fn page_url_of(&self, rel_path: impl AsRef<Path>) -> String {
+        let link = config::page_link(rel_path, self.config.url_style());
+        format!("{}{}", self.site_root(), link)
+    }
+
+    /// Warn about the `styles`/`scripts` of a page that exist in neither
+    /// the source dir nor the static assets
+    fn check_page_assets(&self, rel_md_path: &Path, meta: &markdown::MarkdownMeta) {
+        for url in meta.styles.iter().chain(&meta.scripts) {
+            if markdown::is_absolute_url(url) {
+                continue;
+            }
+
+            let Some(rel_path) = url.strip_prefix('/') else {
+                tracing::warn!(
+                    "{}: page asset should start with `/`: {url}",
+                    rel_md_path.display()
+                );
+                continue;
+            };
+
+            let exists = self.assets.url(url) != url
+                || self.src_dir.join(rel_path).is_file()
+                || self.dst_dir.join(rel_path).is_file();
+            if !exists {
+                tracing::warn!("{}: page asset not found: {url}", rel_md_path.display());
+            }
+        }
+    }
+
+    /// Compose the `<title>` with the `title_suffix` template, or `template` if given
+    fn page_title(&self, title: &str, template: Option<&str>) -> String {
+        let template = template.unwrap_or(&self.config.title_suffix);
+        if template.is_empty() {
+            return title.to_string();
+        }
+
+        template
+            .replace("{title}", title)
+            .replace("{site_name}", &self.config.site_name)
+            .replace("{author}", &self.config.author)
+    }
+
+    fn get_head<'a>(
+        &'a self,
+        title: &'a str,
+        description: Option<&'a str>,
+        canonical: &'a str,
+    ) -> pages::Head<'a> {
+        pages::Head {
+            lang: &self.config.lang,
+            title,
+            description,
+            author: &self.config.author,
+            viewport: &self.config.viewport,
+            theme_color: self.config.theme_color.as_ref(),
+            canonical,
+            translations: None,
+            favicon: &self.config.favicon,
+            feeds: &self.feeds,
+            tag_feed: None,
+            syntax_css: self.config.syntax_css,
+            has_math: false,
+            has_mermaid: false,
+            theme_toggle: self.config.theme_toggle,
+            print_stylesheet: self.config.print_stylesheet,
+            stylesheets: &[],
+            page_styles: &[],
+            page_scripts: &[],
+            assets: &self.assets,
+            hints: &self.config.head_hints,
+        }
     }
 
     fn get_header<'a>(&'a self, active_url: Option<&'a str>) -> pages::Header<'a> {
@@ -463,25 +2034,136 @@ impl Generator {
             home_name: &self.config.header.home_name,
             links: &self.config.header.links,
             active_url,
+            theme_toggle: self.config.theme_toggle,
+            partial: self.partials.header.as_deref(),
         }
     }
     fn get_footer(&self) -> pages::Footer<'_> {
         pages::Footer {
             links: &self.config.footer.links,
             cc_text: &self.config.footer.cc,
+            partial: self.partials.footer.as_deref(),
         }
     }
 }
 
+/// Html of the site replacing the built-in header and footer, see [`config::PARTIALS_DIR`]
+#[derive(Debug, Default)]
+struct Partials {
+    header: Option<String>,
+    footer: Option<String>,
+}
+
+fn read_partial(src_dir: &Path, file_name: &str) -> anyhow::Result<Option<String>> {
+    let path = src_dir.join(config::PARTIALS_DIR).join(file_name);
+    if !path.try_exists()? {
+        return Ok(None);
+    }
+
+    tracing::info!("use partial: {}", path.display());
+    let html = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read partial: {}", path.display()))?;
+    Ok(Some(html))
+}
+
+/// Escape the text and attribute values of the hand-written XML
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// `(slug, name)` of the blog post for the permalink, the frontmatter `slug` overrides both
+fn blog_slug_name(file_name: &str, file_slug: &str, meta_slug: Option<&str>) -> (String, String) {
+    match meta_slug {
+        Some(slug) => (slug.to_string(), slug.to_string()),
+        None => (file_slug.to_string(), file_name.to_string()),
+    }
+}
+
+/// The series of the `name` in `series_list`, by its slug as the series are merged by slug
+fn find_series<'s, 'b>(
+    series_list: &'s BTreeMap<String, Vec<pages::BlogEntry<'b>>>,
+    name: &str,
+) -> Option<(&'s String, &'s Vec<pages::BlogEntry<'b>>)> {
+    let slug = config::slugify_tag(name);
+    series_list
+        .iter()
+        .find(|(name, _)| config::slugify_tag(name) == slug)
+}
+
+/// `SOURCE_DATE_EPOCH` of [reproducible builds](https://reproducible-builds.org/specs/source-date-epoch/)
+/// if set, otherwise `git_time`, the time of the related git commit.
+///
+/// Used for all the build times in the output, the epoch takes precedence over git
+/// so that packagers can pin them.
+fn build_time(
+    git_time: chrono::DateTime<chrono::Utc>,
+) -> anyhow::Result<chrono::DateTime<chrono::Utc>> {
+    let Some(epoch) = std::env::var_os("SOURCE_DATE_EPOCH") else {
+        return Ok(git_time);
+    };
+
+    let epoch = epoch.to_string_lossy();
+    epoch
+        .trim()
+        .parse()
+        .ok()
+        .and_then(|x| chrono::DateTime::from_timestamp(x, 0))
+        .with_context(|| format!("invalid SOURCE_DATE_EPOCH: `{epoch}`"))
+}
+
+/// Copy the file with its permissions and modification time,
+/// so mtime based caching of the deploy tools still works
+fn copy_file(from: impl AsRef<Path>, to: impl AsRef<Path>) -> std::io::Result<()> {
+    let metadata = fs::metadata(&from)?;
+    let mut to = fs::File::create(to)?;
+    std::io::copy(&mut fs::File::open(from)?, &mut to)?;
+
+    // through the handle and before the permissions, a read-only copy cannot be reopened
+    to.set_modified(metadata.modified()?)?;
+    to.set_permissions(metadata.permissions())?;
+
+    Ok(())
+}
+
+/// Recursively collect all files in `dir`
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
 trait RenderIntoExt {
-    fn render_into(&self, output_path: impl AsRef<Path>) -> std::io::Result<usize>;
+    fn render_into(
+        &self,
+        output_path: impl AsRef<Path>,
+        minify_cfg: Option<&minify_html::Cfg>,
+    ) -> std::io::Result<usize>;
 }
 
 impl<T: hypertext::Renderable> RenderIntoExt for T {
-    fn render_into(&self, output_path: impl AsRef<Path>) -> std::io::Result<usize> {
+    /// Render into the file, minified if `minify_cfg` is set
+    fn render_into(
+        &self,
+        output_path: impl AsRef<Path>,
+        minify_cfg: Option<&minify_html::Cfg>,
+    ) -> std::io::Result<usize> {
         let rendered = self.render().into_inner();
 
-        let content = minify_html::minify(rendered.as_bytes(), &minify_html::Cfg::new());
+        let content = match minify_cfg {
+            Some(cfg) => minify_html::minify(rendered.as_bytes(), cfg),
+            None => rendered.into_bytes(),
+        };
 
         if let Some(parent_dir) = output_path.as_ref().parent() {
             fs::create_dir_all(parent_dir)?;
@@ -495,13 +2177,623 @@ impl<T: hypertext::Renderable> RenderIntoExt for T {
     }
 }
 
+fn minify_cfg(config: &config::MinifyConfig) -> minify_html::Cfg {
+    // whitespace in `<pre>` and `<textarea>` is always kept by minify-html
+    minify_html::Cfg {
+        keep_comments: config.keep_comments,
+        keep_closing_tags: config.keep_closing_tags,
+        keep_html_and_head_opening_tags: config.keep_html_and_head_opening_tags,
+        minify_css: config.minify_css,
+        minify_js: config.minify_js,
+        ..minify_html::Cfg::new()
+    }
+}
+
+impl BlogCommit {
+    fn new(commit: &git2::Commit, base_url: &str) -> Self {
+        Self {
+            time: git_repo::git_time_to_datetime(commit.time()),
+            hash: commit.id().to_string(),
+            summary: commit.summary().map(|x| x.to_string()),
+            author_name: commit.author().name().map(|x| x.to_string()),
+            author_email: commit.author().email().map(|x| x.to_string()),
+            base_url: base_url.to_string(),
+        }
+    }
+}
+
 impl BlogEntry {
-    fn as_page(&'_ self) -> pages::BlogEntry<'_> {
+    /// Id of the post for the comment system, from the slug so it's stable across edits
+    /// of the post and changes of the `permalink`
+    fn comment_id(&self) -> String {
+        format!("{}/{}", config::BLOG_DIR, self.slug)
+    }
+
+    /// Distinct commit authors, the original author first. Deduplicated by email,
+    /// or by name without email, the commits without both are skipped
+    fn contributors(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        self.commits
+            .iter()
+            .rev()
+            .filter_map(|commit| {
+                let name = commit.author_name.as_deref().filter(|x| !x.is_empty());
+                let email = commit.author_email.as_deref().filter(|x| !x.is_empty());
+                let key = email.or(name)?;
+                seen.insert(key).then(|| name.or(email)).flatten()
+            })
+            .collect()
+    }
+
+    /// The true latest commit, regardless of `max_commits`
+    fn last_commit(&self) -> Option<&BlogCommit> {
+        self.commits.first()
+    }
+
+    fn as_page<'a>(&'a self, config: &'a Config) -> pages::BlogEntry<'a> {
         pages::BlogEntry {
             publish_time: self.time,
             title: &self.markdown.meta.title,
             rel_path: &self.rel_path,
             tags: &self.markdown.meta.tags,
+            excerpt_html: self.markdown.excerpt_html.as_deref(),
+            url_style: config.url_style(),
+            date_format: &config.date_format,
+        }
+    }
+
+    fn publish_datetime(&self) -> chrono::DateTime<chrono::Utc> {
+        self.time
+            .and_time(chrono::NaiveTime::from_hms_opt(0, 0, 0).unwrap())
+            .and_utc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_repo::tests::commit_all;
+
+    const CONFIG: &str = "\
+author: a
+author_email: a@example.com
+site_name: site
+site_url: https://example.com
+commit_base_url: https://example.com/commit
+minify: false
+header:
+  home_name: home
+  links: []
+footer:
+  links: []
+  cc: cc
+";
+
+    /// A git repo with `config.yaml` and the `files`, all committed
+    fn site(config: &str, files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let required = [
+            ("config.yaml", config),
+            ("home.md", "# home\n"),
+            ("not_found.md", "# not found\n"),
+            ("favicon.svg", "<svg></svg>"),
+        ];
+        // the `files` replace the required ones
+        for (path, content) in required.iter().chain(files) {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        commit_all(&repo, "init", 1_700_000_000);
+        dir
+    }
+
+    /// Build `src` into the `out` dir of a new temp dir
+    fn build(src: &tempfile::TempDir, options: BuildOptions) -> (tempfile::TempDir, BuildReport) {
+        let dir = tempfile::tempdir().unwrap();
+        let report = Generator::new(src.path(), dir.path().join("out"), options)
+            .unwrap()
+            .build()
+            .unwrap();
+        (dir, report)
+    }
+
+    fn read(out: &tempfile::TempDir, rel_path: &str) -> String {
+        fs::read_to_string(out.path().join("out").join(rel_path))
+            .unwrap_or_else(|e| panic!("{rel_path}: {e}"))
+    }
+
+    #[test]
+    fn empty_blog() {
+        // no blog dir, and a blog dir with only a draft
+        let drafts = [("blog/2024-01-01-abc.md", "---\ndraft: true\n---\n# abc\n")];
+        for files in [&[][..], &drafts] {
+            let src = site(CONFIG, files);
+            let (out, report) = build(&src, BuildOptions::default());
+
+            assert_eq!(report.blog_posts, 0);
+            assert!(read(&out, "blog/index.html").contains("<html"));
+            assert!(!out.path().join("out/blog/rss.xml").exists());
+            assert!(out.path().join("out/blog/feed.json").exists());
+            for page in ["index.html", "blog/index.html"] {
+                let html = read(&out, page);
+                assert!(!html.contains("rss.xml"), "{page}");
+                assert!(html.contains("/blog/feed.json"), "{page}");
+            }
+        }
+    }
+
+    #[test]
+    fn uncommitted_posts_without_rss() {
+        let src = site(&format!("{CONFIG}feeds: [rss, atom, json]\n"), &[]);
+        fs::create_dir(src.path().join("blog")).unwrap();
+        fs::write(src.path().join("blog/2024-01-01-abc.md"), "# abc\n").unwrap();
+        let (out, report) = build(&src, BuildOptions::default());
+
+        assert_eq!(report.blog_posts, 1);
+        assert!(!out.path().join("out/blog/rss.xml").exists());
+        assert!(out.path().join("out/blog/atom.xml").exists());
+        for page in ["index.html", "blog/index.html"] {
+            let html = read(&out, page);
+            assert!(!html.contains("rss.xml"), "{page}");
+            assert!(html.contains("/blog/atom.xml"), "{page}");
+        }
+    }
+
+    /// SHA-256 of FIPS 180-4, for the hashes of the inline scripts in a CSP
+    fn sha256(data: &[u8]) -> [u8; 32] {
+        const K: [u32; 64] = [
+            0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+            0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+            0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+            0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+            0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+            0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+            0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+            0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+            0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+            0xc67178f2,
+        ];
+        let mut h: [u32; 8] = [
+            0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+            0x5be0cd19,
+        ];
+
+        let mut message = data.to_vec();
+        message.push(0x80);
+        while message.len() % 64 != 56 {
+            message.push(0);
+        }
+        message.extend((data.len() as u64 * 8).to_be_bytes());
+
+        for chunk in message.chunks(64) {
+            let mut w = [0u32; 64];
+            for (i, word) in chunk.chunks(4).enumerate() {
+                w[i] = u32::from_be_bytes(word.try_into().unwrap());
+            }
+            for i in 16..64 {
+                let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+                let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+                w[i] = w[i - 16]
+                    .wrapping_add(s0)
+                    .wrapping_add(w[i - 7])
+                    .wrapping_add(s1);
+            }
+
+            let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+            for i in 0..64 {
+                let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+                let ch = (e & f) ^ (!e & g);
+                let t1 = hh
+                    .wrapping_add(s1)
+                    .wrapping_add(ch)
+                    .wrapping_add(K[i])
+                    .wrapping_add(w[i]);
+                let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+                let maj = (a & b) ^ (a & c) ^ (b & c);
+                let t2 = s0.wrapping_add(maj);
+                (hh, g, f, e, d, c, b, a) =
+                    (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+            }
+            for (x, y) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+                *x = x.wrapping_add(y);
+            }
+        }
+
+        let mut ret = [0; 32];
+        for (i, x) in h.iter().enumerate() {
+            ret[i * 4..i * 4 + 4].copy_from_slice(&x.to_be_bytes());
+        }
+        ret
+    }
+
+    #[test]
+    fn inline_scripts_allowed_by_default_csp() {
+        use base64::Engine as _;
+
+        // known answer
+        let hash = base64::engine::general_purpose::STANDARD.encode(sha256(b"abc"));
+        assert_eq!(hash, "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=");
+
+        let src = site(&format!("{CONFIG}theme_toggle: true\n"), &[]);
+        for minify in [false, true] {
+            let options = BuildOptions {
+                minify: Some(minify),
+                ..Default::default()
+            };
+            let (out, _) = build(&src, options);
+            let html = read(&out, "index.html");
+
+            let scripts: Vec<&str> = html
+                .split("<script>")
+                .skip(1)
+                .map(|x| &x[..x.find("</script>").unwrap()])
+                .collect();
+            assert!(!scripts.is_empty(), "{html}");
+            for script in scripts {
+                let hash =
+                    base64::engine::general_purpose::STANDARD.encode(sha256(script.as_bytes()));
+                assert!(
+                    my_site_web::DEFAULT_CSP.contains(&format!("'sha256-{hash}'")),
+                    "minify {minify}: `{script}` is not allowed"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn page_styles_and_scripts() {
+        let frontmatter =
+            "---\nstyles: [/demo.css]\nscripts: [https://cdn.example.com/demo.js]\n---\n";
+        let files = [
+            ("demo.css", "p {}"),
+            ("notes/a.md", &format!("{frontmatter}# a\n")),
+            ("blog/2024-01-01-abc.md", &format!("{frontmatter}# abc\n")),
+        ];
+        let src = site(CONFIG, &files);
+        let (out, _) = build(&src, BuildOptions::default());
+
+        for page in ["notes/a/index.html", "blog/2024-01-01-abc/index.html"] {
+            let html = read(&out, page);
+            let style = html.find(r#"href="/demo.css""#);
+            let script = html.find(r#"src="https://cdn.example.com/demo.js""#);
+            assert!(style.is_some() && script.is_some(), "{page}: {html}");
+            // after the global stylesheet
+            let main = html.find(r#"rel="stylesheet" href="/static/styles"#);
+            assert!(main.expect(&html) < style.unwrap(), "{page}");
+        }
+        assert!(!read(&out, "index.html").contains("demo"));
+    }
+
+    /// The internal `href`s of the page with a file in the output
+    fn assert_links_resolve(out: &tempfile::TempDir, page: &str) -> Vec<String> {
+        let html = read(out, page);
+        let hrefs: Vec<String> = html
+            .split("href=\"")
+            .skip(1)
+            .filter_map(|x| x.split('"').next())
+            .filter(|x| x.starts_with('/') && !x.starts_with("//"))
+            .map(|x| x.split(['#', '?']).next().unwrap().to_string())
+            .collect();
+
+        for href in &hrefs {
+            let mut path = out.path().join("out").join(href.trim_start_matches('/'));
+            if href.ends_with('/') {
+                path.push("index.html");
+            }
+            assert!(path.is_file(), "{page}: `{href}` not found");
+        }
+        hrefs
+    }
+
+    #[test]
+    fn links_under_both_url_styles() {
+        let files = [
+            (
+                "notes/a.md",
+                "# a\n\n[b](b.md) [post](../blog/2024-01-01-abc.md)\n",
+            ),
+            ("notes/b.md", "# b\n"),
+            (
+                "blog/2024-01-01-abc.md",
+                "---\ntags: [rust]\n---\n# abc\n\n[a](../notes/a.md)\n",
+            ),
+        ];
+
+        let src = site(CONFIG, &files);
+        let (out, _) = build(&src, BuildOptions::default());
+        let hrefs = assert_links_resolve(&out, "notes/a/index.html");
+        assert!(hrefs.contains(&"/notes/b/".to_string()), "{hrefs:?}");
+        assert!(
+            hrefs.contains(&"/blog/2024-01-01-abc/".to_string()),
+            "{hrefs:?}"
+        );
+        let hrefs = assert_links_resolve(&out, "blog/2024-01-01-abc/index.html");
+        assert!(hrefs.contains(&"/notes/a/".to_string()), "{hrefs:?}");
+        assert!(hrefs.contains(&"/blog/tags/rust/".to_string()), "{hrefs:?}");
+        assert_links_resolve(&out, "blog/index.html");
+        assert!(read(&out, "blog/rss.xml").contains("https://example.com/blog/2024-01-01-abc/"));
+
+        let src = site(&format!("{CONFIG}pretty_urls: false\n"), &files);
+        let (out, _) = build(&src, BuildOptions::default());
+        let hrefs = assert_links_resolve(&out, "notes/a.html");
+        assert!(hrefs.contains(&"/notes/b.html".to_string()), "{hrefs:?}");
+        assert!(
+            hrefs.contains(&"/blog/2024-01-01-abc.html".to_string()),
+            "{hrefs:?}"
+        );
+        let hrefs = assert_links_resolve(&out, "blog/2024-01-01-abc.html");
+        assert!(hrefs.contains(&"/notes/a.html".to_string()), "{hrefs:?}");
+        assert!(
+            hrefs.contains(&"/blog/tags/rust.html".to_string()),
+            "{hrefs:?}"
+        );
+        assert_links_resolve(&out, "blog.html");
+        assert!(
+            read(&out, "blog/rss.xml").contains("https://example.com/blog/2024-01-01-abc.html")
+        );
+    }
+
+    #[test]
+    fn base_path_urls() {
+        let files = [(
+            "blog/2024-01-01-abc.md",
+            "---\ntags: [rust]\nseries: intro\n---\n# abc\n\n[home](../home.md)\n",
+        )];
+        let src = site(&format!("{CONFIG}base_path: /site\n"), &files);
+        let (out, _) = build(&src, BuildOptions::default());
+
+        let canonical = |page: &str| {
+            let html = read(&out, page);
+            let start = html.find(r#"<link rel="canonical" href=""#).expect(&html) + 28;
+            html[start..start + html[start..].find('"').unwrap()].to_string()
+        };
+        let root = "https://example.com/site";
+        assert_eq!(canonical("index.html"), format!("{root}/"));
+        assert_eq!(
+            canonical("blog/2024-01-01-abc/index.html"),
+            format!("{root}/blog/2024-01-01-abc/")
+        );
+        assert_eq!(
+            canonical("blog/tags/rust/index.html"),
+            format!("{root}/blog/tags/rust/")
+        );
+        assert_eq!(
+            canonical("blog/series/intro/index.html"),
+            format!("{root}/blog/series/intro/")
+        );
+
+        let rss = read(&out, "blog/rss.xml");
+        assert!(rss.contains(&format!("<link>{root}/</link>")), "{rss}");
+        let rss = read(&out, "blog/tags/rust/rss.xml");
+        assert!(
+            rss.contains(&format!("<link>{root}/blog/tags/rust/</link>")),
+            "{rss}"
+        );
+        assert!(rss.contains(&format!("{root}/blog/tags/rust/rss.xml")));
+        let json = read(&out, "blog/feed.json");
+        assert!(json.contains(&format!(r#""home_page_url":"{root}/""#)));
+
+        for page in [
+            "index.html",
+            "blog/index.html",
+            "blog/2024-01-01-abc/index.html",
+            "blog/tags/rust/index.html",
+            "blog/series/intro/index.html",
+        ] {
+            let html = read(&out, page);
+            for url in html.split(['"', '\'']).filter(|x| x.starts_with('/')) {
+                let prefixed = url.starts_with("/site/") || url.starts_with("//");
+                assert!(prefixed || url == "/site", "{page}: `{url}`");
+            }
+        }
+    }
+
+    #[test]
+    fn series_slugs() {
+        let files = [
+            (
+                "blog/2024-01-01-abc.md",
+                "---\nseries: Rust Web\n---\n# abc\n",
+            ),
+            (
+                "blog/2024-01-02-def.md",
+                "---\nseries: rust-web\n---\n# def\n",
+            ),
+        ];
+        let src = site(CONFIG, &files);
+        let (out, _) = build(&src, BuildOptions::default());
+
+        // merged under the first one of the newest first posts
+        let html = read(&out, "blog/series/rust-web/index.html");
+        assert!(html.contains("series: rust-web"), "{html}");
+        assert!(html.contains("abc") && html.contains("def"), "{html}");
+        assert!(!out.path().join("out/blog/series/Rust Web").exists());
+
+        for page in [
+            "blog/2024-01-01-abc/index.html",
+            "blog/2024-01-02-def/index.html",
+        ] {
+            let hrefs = assert_links_resolve(&out, page);
+            assert!(hrefs.contains(&"/blog/series/rust-web/".to_string()));
+            // the other part is listed in the series box
+            let html = read(&out, page);
+            let start = html.find("blog-series").expect(&html);
+            let series = &html[start..start + html[start..].find("</ol>").unwrap()];
+            assert_eq!(series.matches("<li").count(), 2, "{page}");
+        }
+    }
+
+    #[test]
+    fn index_md_landing_pages() {
+        let files = [
+            ("index.md", "# root index\n\n[docs](docs/index.md)\n"),
+            (
+                "docs/index.md",
+                "# docs index\n\n[x](x.md) [root](../index.md)\n",
+            ),
+            ("docs/x.md", "# x\n\n[docs](index.md)\n"),
+        ];
+        let config = format!("{CONFIG}special_pages:\n  home: index.md\n");
+        let src = site(&config, &files);
+        let (out, _) = build(&src, BuildOptions::default());
+
+        assert!(read(&out, "index.html").contains("root index"));
+        assert!(read(&out, "docs/index.html").contains("docs index"));
+        assert!(!out.path().join("out/docs/index/index.html").exists());
+        let hrefs = assert_links_resolve(&out, "index.html");
+        assert!(hrefs.contains(&"/docs/".to_string()), "{hrefs:?}");
+        let hrefs = assert_links_resolve(&out, "docs/index.html");
+        assert!(hrefs.contains(&"/docs/x/".to_string()), "{hrefs:?}");
+        assert!(hrefs.contains(&"/".to_string()), "{hrefs:?}");
+        let hrefs = assert_links_resolve(&out, "docs/x/index.html");
+        assert!(hrefs.contains(&"/docs/".to_string()), "{hrefs:?}");
+
+        // the home page replaces a root `index.md` that is not the home page
+        let src = site(CONFIG, &files);
+        let (out, _) = build(&src, BuildOptions::default());
+        assert!(!read(&out, "index.html").contains("root index"));
+        assert!(read(&out, "docs/index.html").contains("docs index"));
+    }
+
+    #[test]
+    fn reproducible_build() {
+        let files = [
+            ("notes/a.md", "# a\n\n```rust\nfn main() {}\n```\n"),
+            (
+                "blog/2024-01-01-abc.md",
+                "---\ntags: [rust, web]\n---\n# abc\n",
+            ),
+            ("blog/2024-01-01-def.md", "---\ntags: [web]\n---\n# def\n"),
+            ("blog/2024-02-01-ghi.md", "---\ntags: [rust]\n---\n# ghi\n"),
+        ];
+        let config = format!("{CONFIG}feeds: [rss, atom, json]\nsearch_index: true\n");
+        let src = site(&config, &files);
+
+        let (first, first_report) = build(&src, BuildOptions::default());
+        let (second, second_report) = build(&src, BuildOptions::default());
+
+        assert_eq!(first_report.output_paths, second_report.output_paths);
+        assert!(
+            first_report
+                .output_paths
+                .contains(&PathBuf::from("search-index.json"))
+        );
+        for rel_path in &first_report.output_paths {
+            let read = |dir: &tempfile::TempDir| fs::read(dir.path().join("out").join(rel_path));
+            assert!(
+                read(&first).unwrap() == read(&second).unwrap(),
+                "{} differs",
+                rel_path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn alias_collisions() {
+        let config = format!("{CONFIG}search_index: true\nredirects_file: true\n");
+        let post = |alias: &str| format!("---\naliases: [{alias}]\n---\n# abc\n");
+        let build_err = |files: &[(&str, &str)]| {
+            let src = site(&config, files);
+            let dir = tempfile::tempdir().unwrap();
+            Generator::new(src.path(), dir.path().join("out"), BuildOptions::default())
+                .unwrap()
+                .build()
+                .unwrap_err()
+                .to_string()
+        };
+
+        // the outputs written after the aliases
+        for alias in ["/search-index.json", "/build-info.json", "/_redirects"] {
+            let err = build_err(&[("blog/2024-01-01-abc.md", &post(alias))]);
+            assert!(err.contains(&format!("alias `{alias}`")), "{err}");
+        }
+
+        let err = build_err(&[
+            ("blog/2024-01-01-abc.md", &post("/old/")),
+            ("blog/2024-01-02-def.md", &post("/old/")),
+        ]);
+        assert!(err.contains("collides with alias `/old/`"), "{err}");
+
+        let src = site(&config, &[("blog/2024-01-01-abc.md", &post("/old/"))]);
+        let (out, _) = build(&src, BuildOptions::default());
+        assert!(read(&out, "old/index.html").contains("/blog/2024-01-01-abc/"));
+        assert!(read(&out, "_redirects").contains("/old/ /blog/2024-01-01-abc/ 301"));
+    }
+
+    #[test]
+    fn absolute_urls_in_feed_items() {
+        let files = [
+            (
+                "blog/2024-01-01-abc.md",
+                "# abc\n\n![img](img.png) [other](https://other.com/)\n",
+            ),
+            ("blog/img.png", ""),
+        ];
+        let src = site(CONFIG, &files);
+        let (out, _) = build(&src, BuildOptions::default());
+
+        let rss = read(&out, "blog/rss.xml");
+        let item = &rss[rss.find("<item>").unwrap()..];
+        // resolved against the page like the browser does
+        assert!(read(&out, "blog/2024-01-01-abc/index.html").contains("src=\"img.png\""));
+        assert!(
+            item.contains("src=\"https://example.com/blog/2024-01-01-abc/img.png\""),
+            "{item}"
+        );
+        assert!(
+            item.contains("href=\"https://example.com/blog/2024-01-01-abc/#abc\""),
+            "{item}"
+        );
+        assert!(item.contains("href=\"https://other.com/\""), "{item}");
+        assert!(!item.contains("src=\"img.png\""), "{item}");
+    }
+
+    #[test]
+    fn update_changed_files() {
+        let files = [
+            ("notes/a.md", "# a\n"),
+            ("notes/b.md", "# b\n"),
+            ("notes/img.png", "1"),
+            ("blog/2024-01-01-abc.md", "# abc\n"),
+        ];
+        let src = site(CONFIG, &files);
+        let (out, _) = build(&src, BuildOptions::default());
+        let update = |changed: &[&str]| {
+            let changed: Vec<_> = changed.iter().map(PathBuf::from).collect();
+            Generator::new(src.path(), out.path().join("out"), BuildOptions::default())
+                .unwrap()
+                .update(&changed)
+                .unwrap()
+        };
+        let b = read(&out, "notes/b/index.html");
+
+        fs::write(src.path().join("notes/a.md"), "# a2\n").unwrap();
+        fs::write(src.path().join("notes/img.png"), "2").unwrap();
+        assert!(update(&["notes/a.md", "notes/img.png"]));
+        assert!(read(&out, "notes/a/index.html").contains("a2"));
+        assert_eq!(read(&out, "notes/img.png"), "2");
+        assert_eq!(read(&out, "notes/b/index.html"), b);
+
+        // the others need a full build
+        fs::write(src.path().join("notes/c.md"), "# c\n").unwrap();
+        fs::write(
+            src.path().join("notes/b.md"),
+            "---\ndraft: true\n---\n# b\n",
+        )
+        .unwrap();
+        for changed in [
+            "blog/2024-01-01-abc.md",
+            "config.yaml",
+            "home.md",
+            "notes/c.md",
+            "notes/b.md",
+            "notes/removed.md",
+        ] {
+            assert!(!update(&[changed]), "{changed}");
         }
+        assert!(!out.path().join("out/notes/c/index.html").exists());
     }
 }
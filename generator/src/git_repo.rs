@@ -1,20 +1,47 @@
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, Mutex},
+};
 
 use anyhow::Context as _;
 use chrono::TimeZone as _;
 
 pub struct GitRepo {
     repo: git2::Repository,
+    /// Shared by the clones of [`GitRepo::try_clone`]
+    semaphore: Arc<Semaphore>,
 }
 
 impl GitRepo {
+    /// Up to [`default_concurrency`] git operations at once
     pub fn new(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::with_concurrency(dir, default_concurrency())
+    }
+
+    /// Up to `limit` git operations at once over this repo and its clones,
+    /// each one opens the pack files it reads
+    pub fn with_concurrency(dir: impl AsRef<Path>, limit: usize) -> anyhow::Result<Self> {
         let dir = dir.as_ref();
 
         let repo = git2::Repository::open(dir)
             .with_context(|| format!("cannot open git repo: {}", dir.display()))?;
 
-        Ok(Self { repo })
+        Ok(Self {
+            repo,
+            semaphore: Arc::new(Semaphore::new(limit)),
+        })
+    }
+
+    /// Open the repo again for another thread, sharing the concurrency limit
+    pub fn try_clone(&self) -> anyhow::Result<Self> {
+        let repo = git2::Repository::open(self.repo.path())
+            .with_context(|| format!("cannot open git repo: {}", self.repo.path().display()))?;
+
+        Ok(Self {
+            repo,
+            semaphore: Arc::clone(&self.semaphore),
+        })
     }
 
     pub fn as_inner(&self) -> &git2::Repository {
@@ -25,15 +52,40 @@ impl GitRepo {
         self.repo
     }
 
+    /// Hash of the `HEAD` commit
+    pub fn head_hash(&self) -> anyhow::Result<String> {
+        let _permit = self.semaphore.acquire();
+        let commit = self
+            .repo
+            .head()?
+            .peel_to_commit()
+            .context("cannot get HEAD commit")?;
+        Ok(commit.id().to_string())
+    }
+
+    /// Commit time of the `HEAD` commit
+    pub fn head_time(&self) -> anyhow::Result<chrono::DateTime<chrono::FixedOffset>> {
+        let _permit = self.semaphore.acquire();
+        let commit = self
+            .repo
+            .head()?
+            .peel_to_commit()
+            .context("cannot get HEAD commit")?;
+        Ok(git_time_to_datetime(commit.time()))
+    }
+
     /// Returns all commits that modified the given file path.
     /// Return empty list if the file not found.
+    #[tracing::instrument(skip_all)]
     pub fn commits_for_file(
         &self,
         file_path: impl AsRef<Path>,
     ) -> anyhow::Result<Vec<git2::Commit<'_>>> {
+        let _permit = self.semaphore.acquire();
         let mut revwalk = self.repo.revwalk()?;
         revwalk.push_head()?;
-        revwalk.set_sorting(git2::Sort::TIME)?;
+        // topological order breaks the ties of the commits at the same time
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
 
         let mut ret = vec![];
 
@@ -66,6 +118,114 @@ impl GitRepo {
 
         Ok(ret)
     }
+
+    /// Returns all commits that modified each file path, newest first,
+    /// same as calling `commits_for_file` for every path but with a single revwalk.
+    ///
+    /// Renames are followed, commits before a rename are listed under the current path.
+    #[tracing::instrument(skip_all)]
+    pub fn commits_by_path(&self) -> anyhow::Result<HashMap<PathBuf, Vec<git2::Commit<'_>>>> {
+        let _permit = self.semaphore.acquire();
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push_head()?;
+        // topological order breaks the ties of the commits at the same time
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+
+        let mut ret: HashMap<PathBuf, Vec<git2::Commit<'_>>> = HashMap::new();
+        // old path -> current path, filled while walking back in time
+        let mut renamed_to: HashMap<PathBuf, PathBuf> = HashMap::new();
+
+        for oid in revwalk {
+            let oid = oid?;
+
+            let commit = self.repo.find_commit(oid)?;
+            let tree = commit.tree()?;
+
+            // Compare with parent
+            let parent_tree = if commit.parent_count() > 0 {
+                Some(commit.parent(0)?.tree()?)
+            } else {
+                None
+            };
+
+            let mut diff = self
+                .repo
+                .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+            diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))?;
+
+            for delta in diff.deltas() {
+                if delta.status() != git2::Delta::Renamed {
+                    continue;
+                }
+                if let (Some(old), Some(new)) = (delta.old_file().path(), delta.new_file().path()) {
+                    let current = renamed_to.get(new).cloned().unwrap_or_else(|| new.into());
+                    renamed_to.insert(old.into(), current);
+                }
+            }
+
+            // a pathspec matches both sides of a delta
+            let mut paths: Vec<&Path> = diff
+                .deltas()
+                .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+                .flatten()
+                .map(|path| renamed_to.get(path).map_or(path, PathBuf::as_path))
+                .collect();
+            paths.sort();
+            paths.dedup();
+
+            for path in paths {
+                ret.entry(path.to_path_buf())
+                    .or_default()
+                    .push(commit.clone());
+            }
+        }
+
+        Ok(ret)
+    }
+}
+
+/// Number of CPUs, or 1 if unknown
+pub fn default_concurrency() -> usize {
+    std::thread::available_parallelism().map_or(1, usize::from)
+}
+
+/// Counting semaphore bounding the concurrent git operations
+struct Semaphore {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+/// Released on drop
+struct Permit<'a>(&'a Semaphore);
+
+impl Semaphore {
+    /// At least 1 permit, otherwise nothing could run
+    fn new(permits: usize) -> Self {
+        Self {
+            available: Mutex::new(permits.max(1)),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Block until a permit is available
+    fn acquire(&self) -> Permit<'_> {
+        let mut available = self.available.lock().unwrap_or_else(|x| x.into_inner());
+        while *available == 0 {
+            available = self
+                .released
+                .wait(available)
+                .unwrap_or_else(|x| x.into_inner());
+        }
+        *available -= 1;
+        Permit(self)
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self.0.available.lock().unwrap_or_else(|x| x.into_inner()) += 1;
+        self.0.released.notify_one();
+    }
 }
 
 pub fn git_time_to_datetime(time: git2::Time) -> chrono::DateTime<chrono::FixedOffset> {
@@ -79,3 +239,110 @@ pub fn git_time_to_datetime(time: git2::Time) -> chrono::DateTime<chrono::FixedO
         .single()
         .expect("invalid timestamp")
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Commit all the changes in the working tree of `repo` at `time` (unix seconds),
+    /// like `git add -A && git commit`
+    pub(crate) fn commit_all(repo: &git2::Repository, message: &str, time: i64) -> git2::Oid {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.update_all(["*"], None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+
+        let signature =
+            git2::Signature::new("a", "a@example.com", &git2::Time::new(time, 0)).unwrap();
+        let parent = repo.head().ok().and_then(|x| x.peel_to_commit().ok());
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn oldest_commit_across_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let content = "# abc\n\nsome text long enough to be detected as a rename\n";
+
+        std::fs::write(dir.path().join("old.md"), content).unwrap();
+        let created = commit_all(&repo, "create", 1_000);
+        std::fs::write(dir.path().join("other.md"), "# other\n").unwrap();
+        commit_all(&repo, "other", 2_000);
+        std::fs::rename(dir.path().join("old.md"), dir.path().join("new.md")).unwrap();
+        commit_all(&repo, "rename", 3_000);
+        std::fs::write(dir.path().join("new.md"), format!("{content}more\n")).unwrap();
+        let edited = commit_all(&repo, "edit", 4_000);
+
+        let git_repo = GitRepo::new(dir.path()).unwrap();
+        let commits = git_repo.commits_by_path().unwrap();
+        let ids: Vec<_> = commits[Path::new("new.md")]
+            .iter()
+            .map(|x| x.id())
+            .collect();
+        assert_eq!(ids.len(), 3);
+        assert_eq!(ids.first(), Some(&edited));
+        assert_eq!(ids.last(), Some(&created));
+        assert!(!commits.contains_key(Path::new("old.md")));
+        assert_eq!(commits[Path::new("other.md")].len(), 1);
+    }
+
+    #[test]
+    fn semaphore_bounds_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let semaphore = Semaphore::new(2);
+        let active = AtomicUsize::new(0);
+        let max_active = AtomicUsize::new(0);
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let _permit = semaphore.acquire();
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_active.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        assert_eq!(max_active.load(Ordering::SeqCst), 2);
+        assert_eq!(*semaphore.available.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn clones_share_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        for i in 0..10 {
+            std::fs::write(dir.path().join(format!("{i}.md")), format!("# {i}\n")).unwrap();
+            commit_all(&repo, "add", 1_000 + i);
+        }
+
+        let git_repo = GitRepo::with_concurrency(dir.path(), 2).unwrap();
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..16)
+                .map(|_| {
+                    let git_repo = git_repo.try_clone().unwrap();
+                    scope.spawn(move || git_repo.commits_by_path().unwrap().len())
+                })
+                .collect();
+            for handle in handles {
+                assert_eq!(handle.join().unwrap(), 10);
+            }
+        });
+        assert_eq!(*git_repo.semaphore.available.lock().unwrap(), 2);
+    }
+}
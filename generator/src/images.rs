@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use base64::Engine as _;
+
+/// Extensions of the images that get resized variants, SVGs scale by themselves
+/// and GIFs would lose the animation
+const RESIZABLE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+
+/// Max width and height of the placeholders, a few hundred bytes as PNG
+const PLACEHOLDER_SIZE: u32 = 16;
+
+pub fn is_resizable(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| RESIZABLE_EXTENSIONS.contains(&x.to_ascii_lowercase().as_str()))
+}
+
+/// Widths of the variants of an image `original_width` wide, only downscaled
+pub fn variant_widths(widths: &[u32], original_width: u32) -> impl Iterator<Item = u32> + '_ {
+    widths.iter().copied().filter(move |x| *x < original_width)
+}
+
+/// `a/b.png` -> `a/b-480w.png`, works for both paths and urls
+pub fn variant_url(url: &str, width: u32) -> String {
+    let file_start = url.rfind('/').map_or(0, |x| x + 1);
+    match url[file_start..].rfind('.') {
+        Some(dot) => {
+            let dot = file_start + dot;
+            format!("{}-{width}w{}", &url[..dot], &url[dot..])
+        }
+        None => format!("{url}-{width}w"),
+    }
+}
+
+fn variant_path(path: &Path, width: u32) -> PathBuf {
+    PathBuf::from(variant_url(&path.to_string_lossy(), width))
+}
+
+/// Write the downscaled variants of `src` next to `dst`
+pub fn write_variants(src: &Path, dst: &Path, widths: &[u32]) -> anyhow::Result<()> {
+    let (original_width, _) = image::image_dimensions(src)
+        .with_context(|| format!("failed to read image: {}", src.display()))?;
+    if variant_widths(widths, original_width).next().is_none() {
+        return Ok(());
+    }
+
+    let img =
+        image::open(src).with_context(|| format!("failed to read image: {}", src.display()))?;
+
+    for width in variant_widths(widths, original_width) {
+        let height =
+            (u64::from(img.height()) * u64::from(width) / u64::from(original_width)).max(1);
+        let resized = img.resize(width, height as u32, image::imageops::FilterType::Lanczos3);
+
+        let path = variant_path(dst, width);
+        resized
+            .save(&path)
+            .with_context(|| format!("failed to write image: {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Tiny blurred copy of the image as a `data:` url, shown until the image is loaded.
+/// `None` for the images with transparency, the placeholder would show through.
+pub fn placeholder(path: &Path) -> anyhow::Result<Option<String>> {
+    let img =
+        image::open(path).with_context(|| format!("failed to read image: {}", path.display()))?;
+    if img.color().has_alpha() {
+        return Ok(None);
+    }
+
+    let img = img.thumbnail(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE).blur(1.0);
+
+    let mut png = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut png, image::ImageFormat::Png)?;
+
+    let data = base64::engine::general_purpose::STANDARD.encode(png.into_inner());
+    Ok(Some(format!("data:image/png;base64,{data}")))
+}
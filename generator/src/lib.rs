@@ -5,12 +5,80 @@ use std::path::PathBuf;
 mod config;
 mod generator;
 mod git_repo;
+mod images;
+mod link_checker;
 mod markdown;
 mod pages;
+mod search_index;
 mod static_dir;
 
+pub use config::{Layout, ThemeColor};
+pub use generator::BuildReport;
+/// The markdown pipeline of the site (comrak, syntax highlight, frontmatter),
+/// usable without building a whole site
+pub use markdown::{
+    Highlighter, Markdown, MarkdownMeta, MarkdownOptions, read_md, render_markdown_str,
+};
+
+/// Build the site in `in_dir` into `out_dir` with the default options,
+/// use [`Builder`] to get the [`BuildReport`]
 pub fn build(in_dir: impl Into<PathBuf>, out_dir: impl Into<PathBuf>) -> anyhow::Result<()> {
-    let generator = generator::Generator::new(in_dir, out_dir)?;
-    generator.build()?;
+    Builder::new(in_dir, out_dir).build()?;
     Ok(())
 }
+
+/// Configure a build without editing the config file,
+/// e.g. `Builder::new(in_dir, out_dir).with_drafts(true).build()`
+#[derive(Debug, Clone)]
+pub struct Builder {
+    in_dir: PathBuf,
+    out_dir: PathBuf,
+    options: generator::BuildOptions,
+}
+
+impl Builder {
+    pub fn new(in_dir: impl Into<PathBuf>, out_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            in_dir: in_dir.into(),
+            out_dir: out_dir.into(),
+            options: generator::BuildOptions::default(),
+        }
+    }
+
+    /// Also build the pages with `draft: true` in the frontmatter
+    pub fn with_drafts(mut self, drafts: bool) -> Self {
+        self.options.drafts = drafts;
+        self
+    }
+
+    /// Override `site_url` in the config, e.g. for a preview deployment
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.options.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Override `minify` in the config, the raw rendered html is easier to inspect and diff
+    pub fn with_minify(mut self, minify: bool) -> Self {
+        self.options.minify = Some(minify);
+        self
+    }
+
+    /// Override `git_concurrency` in the config, for large repos running out of file descriptors
+    pub fn with_git_concurrency(mut self, limit: usize) -> Self {
+        self.options.git_concurrency = Some(limit);
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<BuildReport> {
+        let generator = generator::Generator::new(self.in_dir, self.out_dir, self.options)?;
+        generator.build()
+    }
+
+    /// Update the output of a previous build in `out_dir` for the `changed` source files,
+    /// relative to `in_dir`. Returns `false` if a change affects other pages,
+    /// e.g. a blog post or the config, and the whole site has to be built again
+    pub fn update(self, changed: &[PathBuf]) -> anyhow::Result<bool> {
+        let generator = generator::Generator::new(self.in_dir, self.out_dir, self.options)?;
+        generator.update(changed)
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use normalize_path::NormalizePath as _;
+
+/// Check if internal links (`href` and `src`) in the generated HTML files exist.
+pub struct LinkChecker {
+    dst_dir: PathBuf,
+    check_anchors: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct BrokenLink {
+    /// The page that contains the link, relative to the output dir
+    pub page: PathBuf,
+    pub target: String,
+}
+
+#[derive(Debug, Default)]
+struct Page {
+    links: Vec<String>,
+    ids: HashSet<String>,
+}
+
+impl LinkChecker {
+    pub fn new(dst_dir: impl Into<PathBuf>, check_anchors: bool) -> Self {
+        Self {
+            dst_dir: dst_dir.into(),
+            check_anchors,
+        }
+    }
+
+    pub fn check(&self) -> anyhow::Result<Vec<BrokenLink>> {
+        let mut pages = HashMap::new();
+        self.read_pages(&self.dst_dir, &mut pages)?;
+
+        // sort to report in a stable order
+        let mut page_paths: Vec<_> = pages.keys().collect();
+        page_paths.sort();
+
+        let mut ret = vec![];
+
+        for page_path in page_paths {
+            for link in &pages[page_path].links {
+                if is_external(link) || self.is_valid(page_path, link, &pages) {
+                    continue;
+                }
+
+                ret.push(BrokenLink {
+                    page: page_path.clone(),
+                    target: link.clone(),
+                });
+            }
+        }
+
+        Ok(ret)
+    }
+
+    fn read_pages(&self, dir: &Path, pages: &mut HashMap<PathBuf, Page>) -> anyhow::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                self.read_pages(&path, pages)?;
+            } else if path.extension().and_then(|x| x.to_str()) == Some("html") {
+                let html = fs::read_to_string(&path)?;
+                let page = read_page(&html)
+                    .with_context(|| format!("failed to read html: {}", path.display()))?;
+
+                let rel_path = path.strip_prefix(&self.dst_dir)?.to_path_buf();
+                pages.insert(rel_path, page);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if the local assets (`src`, `srcset` and `poster`) in the `<main>` of the pages
+    /// exist, `pages` are relative to the output dir
+    pub fn check_assets<'a>(
+        &self,
+        pages: impl IntoIterator<Item = &'a Path>,
+    ) -> anyhow::Result<Vec<BrokenLink>> {
+        let mut ret = vec![];
+
+        for page_path in pages {
+            let html = fs::read_to_string(self.dst_dir.join(page_path))
+                .with_context(|| format!("failed to read html: {}", page_path.display()))?;
+
+            for url in read_assets(&html)? {
+                let path = url.split(['?', '#']).next().unwrap_or_default();
+                if is_external(&url) || self.dst_dir.join(self.resolve(page_path, path)).is_file() {
+                    continue;
+                }
+
+                ret.push(BrokenLink {
+                    page: page_path.to_path_buf(),
+                    target: url,
+                });
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Output file of the non-empty `path` linked from `page_path`, relative to the output dir
+    fn resolve(&self, page_path: &Path, path: &str) -> PathBuf {
+        // relative links are relative to the dir of the page,
+        // since `abc/index.html` is served as `abc/`
+        let base = if path.starts_with('/') {
+            Path::new("")
+        } else {
+            page_path.parent().unwrap_or(Path::new(""))
+        };
+
+        let p = base.join(path.trim_start_matches('/')).normalize();
+        if self.dst_dir.join(&p).is_dir() {
+            p.join("index.html")
+        } else {
+            p
+        }
+    }
+
+    /// `page_path` is the page that contains `link`
+    fn is_valid(&self, page_path: &Path, link: &str, pages: &HashMap<PathBuf, Page>) -> bool {
+        let (path, anchor) = link.split_once('#').unwrap_or((link, ""));
+        let path = path.split('?').next().unwrap_or_default();
+
+        let target = if path.is_empty() {
+            page_path.to_path_buf()
+        } else {
+            self.resolve(page_path, path)
+        };
+
+        if !self.dst_dir.join(&target).is_file() {
+            return false;
+        }
+
+        if !self.check_anchors || anchor.is_empty() {
+            return true;
+        }
+
+        // only check anchors on html pages
+        pages.get(&target).is_none_or(|x| x.ids.contains(anchor))
+    }
+}
+
+/// Links with a scheme (`https:`, `mailto:`, `data:` etc.) or protocol-relative links
+fn is_external(link: &str) -> bool {
+    link.starts_with("//")
+        || link
+            .split_once(':')
+            .is_some_and(|(scheme, _)| !scheme.contains(['/', '?', '#']))
+}
+
+fn read_page(html: &str) -> anyhow::Result<Page> {
+    let mut page = Page::default();
+
+    lol_html::rewrite_str(
+        html,
+        lol_html::RewriteStrSettings {
+            element_content_handlers: vec![lol_html::element!("*", |el| {
+                for attr in ["href", "src"] {
+                    if let Some(link) = el.get_attribute(attr).filter(|x| !x.is_empty()) {
+                        page.links.push(link);
+                    }
+                }
+                if let Some(id) = el.get_attribute("id") {
+                    page.ids.insert(id);
+                }
+                Ok(())
+            })],
+            ..lol_html::RewriteStrSettings::new()
+        },
+    )?;
+
+    Ok(page)
+}
+
+/// Urls of the assets in `<main>`, the candidates of `srcset` included
+fn read_assets(html: &str) -> anyhow::Result<Vec<String>> {
+    let mut urls = vec![];
+
+    lol_html::rewrite_str(
+        html,
+        lol_html::RewriteStrSettings {
+            element_content_handlers: vec![lol_html::element!("main *", |el| {
+                for attr in ["src", "poster"] {
+                    if let Some(url) = el.get_attribute(attr).filter(|x| !x.is_empty()) {
+                        urls.push(url);
+                    }
+                }
+                if let Some(srcset) = el.get_attribute("srcset") {
+                    let candidates = srcset
+                        .split(',')
+                        .filter_map(|x| x.split_whitespace().next());
+                    urls.extend(candidates.map(str::to_string));
+                }
+                Ok(())
+            })],
+            ..lol_html::RewriteStrSettings::new()
+        },
+    )?;
+
+    Ok(urls)
+}
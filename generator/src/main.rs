@@ -1,30 +1,332 @@
-use std::{env, path::Path, time::Duration};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock, mpsc},
+    time::Duration,
+};
 
 use anyhow::Context as _;
-use my_site_generator::build;
+use my_site_generator::Builder;
+use notify::Watcher as _;
+use tower::ServiceExt as _;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// Wait this long without any changes before rebuilding
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+const SERVE_DEFAULT_PORT: u16 = 5000;
 
 fn main() -> anyhow::Result<()> {
-    env_logger::builder()
-        .filter_level(log::LevelFilter::Info)
-        .init();
+    tracing_setup();
 
     let args: Vec<String> = env::args().collect();
 
     let name = &args[0];
-    let src_dir = &args.get(1).with_context(|| help(name))?;
-    let dst_dir = &args.get(2).with_context(|| help(name))?;
+
+    if args.get(1).is_some_and(|x| x == "serve") {
+        let src_dir = args.get(2).with_context(|| help(name))?;
+        let port = match args.get(3) {
+            Some(port) => port.parse().context("failed to parse port")?,
+            None => SERVE_DEFAULT_PORT,
+        };
+        return serve(src_dir, port);
+    }
+
+    let watch = args.iter().skip(1).any(|x| x == "--watch");
+    let minify = args
+        .iter()
+        .skip(1)
+        .any(|x| x == "--no-minify")
+        .then_some(false);
+    let args: Vec<_> = args
+        .iter()
+        .skip(1)
+        .filter(|x| *x != "--watch" && *x != "--no-minify")
+        .collect();
+
+    let src_dir = args.first().with_context(|| help(name))?;
+    let dst_dir = args.get(1).with_context(|| help(name))?;
 
     if Path::new(dst_dir).exists() {
-        log::warn!("dest dir `{}` already exists, delete it...", dst_dir);
+        tracing::warn!("dest dir `{}` already exists, delete it...", dst_dir);
         std::thread::sleep(Duration::from_secs(1));
         let _res = std::fs::remove_dir_all(dst_dir);
     }
 
-    build(src_dir, dst_dir)?;
+    if !watch {
+        return build(src_dir, dst_dir, minify);
+    }
 
-    Ok(())
+    if let Err(err) = build(src_dir, dst_dir, minify) {
+        tracing::error!("failed to build: {err:?}");
+    }
+
+    watch_and_rebuild(src_dir, dst_dir, minify)
 }
 
 fn help(name: &str) -> String {
-    format!("Usage: {} <src-dir> <dst-dir>", name)
+    format!(
+        "Usage: {name} [--watch] [--no-minify] <src-dir> <dst-dir>\n       {name} serve <src-dir> [port]"
+    )
+}
+
+/// Build with `minify` overriding the config if set
+fn build(
+    src_dir: impl AsRef<Path>,
+    dst_dir: impl AsRef<Path>,
+    minify: Option<bool>,
+) -> anyhow::Result<()> {
+    builder(src_dir, dst_dir, minify).build()?;
+    Ok(())
+}
+
+fn builder(src_dir: impl AsRef<Path>, dst_dir: impl AsRef<Path>, minify: Option<bool>) -> Builder {
+    let builder = Builder::new(src_dir.as_ref(), dst_dir.as_ref());
+    match minify {
+        Some(minify) => builder.with_minify(minify),
+        None => builder,
+    }
+}
+
+/// Build into a temp dir and serve it, rebuild whenever files in `src_dir` changed.
+///
+/// The served app is replaced after each full build, to reload `server.json` and `redirects.json`.
+fn serve(src_dir: &str, port: u16) -> anyhow::Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let mut site = ServedSite::new(src_dir, tmp_dir.path())?;
+
+    if let Err(err) = site.build() {
+        tracing::error!("failed to build: {err:?}");
+    }
+    let app = Arc::new(RwLock::new(site.app()?));
+
+    let reload = Arc::clone(&app);
+    std::thread::spawn(move || {
+        // avoid rebuild loops
+        let ignored = [site.src_dir.join(".git"), site.root.clone()];
+        let res = watch(&site.src_dir.clone(), &ignored, |changed| {
+            if site.update(changed)? {
+                return Ok(());
+            }
+            tracing::info!("rebuild the site");
+            site.build()?;
+            *reload.write().unwrap_or_else(PoisonError::into_inner) = site.app()?;
+            Ok(())
+        });
+        if let Err(err) = res {
+            tracing::error!("stop watching: {err:?}");
+        }
+    });
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("failed to listen on address: {}", addr))?;
+
+        tracing::info!("serve at http://{}", addr);
+
+        // the latest app for each request
+        let app = axum::Router::new().fallback(async move |req: axum::extract::Request| {
+            let app = app.read().unwrap_or_else(PoisonError::into_inner).clone();
+            app.oneshot(req).await
+        });
+
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = tokio::signal::ctrl_c().await;
+            })
+            .await
+            .context("failed to serve")
+    })
+}
+
+/// Output of `serve` in `root`: each full build goes into a new dir, and the served
+/// `root/site` is a link switched to it once it's done, so the pages never go missing
+struct ServedSite {
+    src_dir: PathBuf,
+    root: PathBuf,
+    /// number of the current build dir
+    builds: usize,
+}
+
+impl ServedSite {
+    fn new(src_dir: impl AsRef<Path>, root: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(Self {
+            src_dir: std::path::absolute(src_dir)?,
+            root: std::path::absolute(root)?,
+            builds: 0,
+        })
+    }
+
+    /// The served link
+    fn served_dir(&self) -> PathBuf {
+        self.root.join("site")
+    }
+
+    fn build_dir(&self, n: usize) -> PathBuf {
+        self.root.join(format!("build-{n}"))
+    }
+
+    /// Build into a new dir and switch the served link to it
+    fn build(&mut self) -> anyhow::Result<()> {
+        let dir = self.build_dir(self.builds + 1);
+        if let Err(err) = build(&self.src_dir, &dir, None) {
+            let _res = std::fs::remove_dir_all(&dir);
+            return Err(err);
+        }
+
+        // renaming over the old link is atomic
+        let link = self.root.join("site.new");
+        std::os::unix::fs::symlink(&dir, &link)?;
+        std::fs::rename(&link, self.served_dir())?;
+
+        let old = self.build_dir(self.builds);
+        self.builds += 1;
+        if old.exists() {
+            std::fs::remove_dir_all(old)?;
+        }
+
+        Ok(())
+    }
+
+    /// Update the current build in place if the changes don't affect other pages
+    fn update(&self, changed: &[PathBuf]) -> anyhow::Result<bool> {
+        let dir = self.build_dir(self.builds);
+        if !dir.exists() {
+            return Ok(false);
+        }
+
+        let updated =
+            builder(&self.src_dir, dir, None).update(&rel_paths(&self.src_dir, changed))?;
+        if updated {
+            tracing::info!("updated the changed files only");
+        }
+        Ok(updated)
+    }
+
+    fn app(&self) -> anyhow::Result<axum::Router> {
+        my_site_web::app(
+            self.served_dir(),
+            my_site_web::NOT_FOUND_PAGE_FILE,
+            my_site_web::ERROR_PAGE_FILE,
+            &my_site_web::AppOptions::default(),
+        )
+    }
+}
+
+/// Rebuild whenever files in `src_dir` changed, only the changed files
+/// if they don't affect the other pages.
+fn watch_and_rebuild(
+    src_dir: impl AsRef<Path>,
+    dst_dir: impl AsRef<Path>,
+    minify: Option<bool>,
+) -> anyhow::Result<()> {
+    let src_dir = std::path::absolute(src_dir)?;
+    let dst_dir = std::path::absolute(dst_dir)?;
+
+    // avoid rebuild loops
+    let ignored = [src_dir.join(".git"), dst_dir.clone()];
+
+    watch(&src_dir, &ignored, |changed| {
+        rebuild(&src_dir, &dst_dir, changed, minify)
+    })
+}
+
+/// Call `on_change` with the changed paths whenever files in `src_dir` changed,
+/// except the `ignored` paths. Errors are logged and the watching goes on.
+fn watch(
+    src_dir: &Path,
+    ignored: &[PathBuf],
+    mut on_change: impl FnMut(&[PathBuf]) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher
+        .watch(src_dir, notify::RecursiveMode::Recursive)
+        .with_context(|| format!("failed to watch dir: {}", src_dir.display()))?;
+
+    tracing::info!("watching for changes: {}", src_dir.display());
+
+    while let Ok(res) = rx.recv() {
+        let mut changed = changed_paths(res, ignored);
+        if changed.is_empty() {
+            continue;
+        }
+
+        // wait until the changes settle down
+        while let Ok(res) = rx.recv_timeout(WATCH_DEBOUNCE) {
+            changed.extend(changed_paths(res, ignored));
+        }
+        changed.sort();
+        changed.dedup();
+
+        for path in &changed {
+            tracing::info!("file changed: {}", path.display());
+        }
+        if let Err(err) = on_change(&changed) {
+            tracing::error!("failed to build: {err:?}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Update the output for the `changed` paths, or build the whole site again
+fn rebuild(
+    src_dir: &Path,
+    dst_dir: &Path,
+    changed: &[PathBuf],
+    minify: Option<bool>,
+) -> anyhow::Result<()> {
+    let changed = rel_paths(src_dir, changed);
+    if dst_dir.exists() && builder(src_dir, dst_dir, minify).update(&changed)? {
+        tracing::info!("updated the changed files only");
+        return Ok(());
+    }
+
+    tracing::info!("rebuild the site");
+    if dst_dir.exists() {
+        std::fs::remove_dir_all(dst_dir)?;
+    }
+    build(src_dir, dst_dir, minify)
+}
+
+/// The `paths` in `src_dir` relative to it
+fn rel_paths(src_dir: &Path, paths: &[PathBuf]) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .filter_map(|x| x.strip_prefix(src_dir).ok())
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// The changed paths of the event which aren't ignored
+fn changed_paths(res: notify::Result<notify::Event>, ignored: &[PathBuf]) -> Vec<PathBuf> {
+    let Ok(event) = res.inspect_err(|err| tracing::warn!("watch error: {err}")) else {
+        return Vec::new();
+    };
+
+    // the build itself reads the source files
+    if event.kind.is_access() {
+        return Vec::new();
+    }
+
+    event
+        .paths
+        .into_iter()
+        .filter(|p| !ignored.iter().any(|i| p.starts_with(i)))
+        .collect()
+}
+
+/// Log with `RUST_LOG`, and report the elapsed time when a per-file span is closed
+fn tracing_setup() {
+    let env_filter =
+        tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into());
+
+    tracing_subscriber::fmt()
+        .with_env_filter(env_filter)
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
 }
@@ -1,22 +1,246 @@
 use std::{
+    collections::BTreeMap,
+    ops::RangeInclusive,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, LazyLock},
 };
 
 use anyhow::Context as _;
-use comrak::{Arena, Node, nodes::NodeValue, plugins::syntect::SyntectAdapter};
+use comrak::{
+    Arena, Node,
+    nodes::NodeValue,
+    plugins::syntect::{SyntectAdapter, SyntectAdapterBuilder},
+};
 use normalize_path::NormalizePath as _;
 use serde::Deserialize;
 
+use crate::{config, images};
+
+#[tracing::instrument(skip_all)]
 pub fn read_md(
     base_dir: impl Into<PathBuf>,
     file_path: impl Into<PathBuf>,
+    md_options: &MarkdownOptions,
 ) -> anyhow::Result<Markdown> {
-    let source = MarkdownSource::new(base_dir, file_path)?;
+    let source = MarkdownSource::new(base_dir, file_path, md_options.clone())?;
+    render(&source)
+}
+
+/// Render the markdown `input` with the same pipeline as the pages of the site,
+/// without a source dir. Relative links and images are resolved against the current dir.
+pub fn render_markdown_str(input: &str, md_options: &MarkdownOptions) -> anyhow::Result<Markdown> {
+    let source = MarkdownSource::from_content("", STR_INPUT_PATH, input, md_options.clone());
+    render(&source)
+}
+
+/// Name of the string input of [`render_markdown_str`] in the messages
+const STR_INPUT_PATH: &str = "<input>.md";
+
+fn render<'a>(source: &'a MarkdownSource<'a>) -> anyhow::Result<Markdown> {
+    let md_options = &source.md_options;
     let ast = source.parse();
+    ast.resolve_wikilinks();
+    if md_options.twemoji {
+        ast.shortcodes_to_twemoji();
+    }
     let meta = ast.to_meta()?;
     let html = ast.to_html()?;
-    Ok(Markdown { meta, html })
+    let html = source.rewrite_images(&html)?;
+    let html = if md_options.twemoji {
+        rewrite_twemoji(&html)?
+    } else {
+        html
+    };
+    let html = source.rewrite_footnotes(&html)?;
+    let html = rewrite_mermaid(&html);
+    let html = source.rewrite_code_lines(&html);
+    let html = source.rewrite_code_blocks(&html)?;
+    let html = source.rewrite_heading_anchors(&html)?;
+    let html = source.rewrite_external_links(&html)?;
+    let excerpt_html = ast.to_excerpt_html()?;
+    let has_math = ast.has_math();
+    let has_mermaid = ast.has_mermaid();
+
+    if let Some(canonical) = meta.canonical.as_deref().filter(|x| !is_absolute_url(x)) {
+        tracing::warn!(
+            "canonical url `{}` in {} is not an absolute url",
+            canonical,
+            source.file_path.display()
+        );
+    }
+
+    Ok(Markdown {
+        meta,
+        html,
+        excerpt_html,
+        has_math,
+        has_mermaid,
+    })
+}
+
+/// Read only the meta of the markdown file without rendering it
+pub fn read_meta(
+    base_dir: impl Into<PathBuf>,
+    file_path: impl Into<PathBuf>,
+    md_options: &MarkdownOptions,
+) -> anyhow::Result<MarkdownMeta> {
+    let source = MarkdownSource::new(base_dir, file_path, md_options.clone())?;
+    source.parse().to_meta()
+}
+
+/// Page names are matched case-insensitively, ignoring punctuation and spaces,
+/// e.g. `Some Page`, `some-page` and `some_page` are the same
+pub fn wikilink_key(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Check if `url` is a real url (not a path), e.g. `https://...` or `mailto:...`
+fn is_real_url(url: &str) -> bool {
+    url.contains("://") || url.starts_with("mailto:")
+}
+
+/// Check if `url` is a real url to another site, `mailto:` is not external
+fn is_external_url(url: &str, site_url: &str) -> bool {
+    is_real_url(url) && !url.starts_with("mailto:") && !is_site_url(url, site_url)
+}
+
+/// `https://example.com/a` is on `https://example.com`, `https://example.com.evil/` is not
+fn is_site_url(url: &str, site_url: &str) -> bool {
+    let site_url = site_url.trim_end_matches('/');
+    !site_url.is_empty()
+        && url
+            .strip_prefix(site_url)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?', '#']))
+}
+
+/// Check if `url` is an absolute `http(s)://` url
+pub fn is_absolute_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// Rewrite the relative `href`/`src` in `html` to absolute urls, for the content
+/// read out of the page like feeds. `page_path` is the path the page is served at
+/// (e.g. `/blog/abc/`), relative urls are resolved against its directory.
+///
+/// Urls with a scheme (`https:`, `mailto:`, `data:`, etc.) are kept.
+pub fn absolute_urls(html: &str, site_url: &str, page_path: &str) -> anyhow::Result<String> {
+    let dir = match page_path.rfind('/') {
+        Some(i) => &page_path[..=i],
+        None => "/",
+    };
+
+    let resolve = |url: &str| -> Option<String> {
+        let has_scheme = url
+            .split_once(':')
+            .is_some_and(|(scheme, _)| !scheme.contains(['/', '?', '#']));
+        if url.is_empty() || has_scheme || url.starts_with("//") {
+            return None;
+        }
+
+        let path = if url.starts_with('/') {
+            url.to_string()
+        } else if url.starts_with(['#', '?']) {
+            format!("{page_path}{url}")
+        } else {
+            let p = Path::new(dir).join(url).normalize();
+            p.to_str().unwrap_or_default().to_string()
+        };
+
+        Some(format!("{site_url}{path}"))
+    };
+
+    let html = lol_html::rewrite_str(
+        html,
+        lol_html::RewriteStrSettings {
+            element_content_handlers: vec![lol_html::element!("*", |el| {
+                for attr in ["href", "src", "poster"] {
+                    if let Some(url) = el.get_attribute(attr).and_then(|x| resolve(&x)) {
+                        el.set_attribute(attr, &url)?;
+                    }
+                }
+                Ok(())
+            })],
+            ..lol_html::RewriteStrSettings::new()
+        },
+    )?;
+
+    Ok(html)
+}
+
+const TWEMOJI_BASE_URL: &str = "https://cdn.jsdelivr.net/gh/jdecked/twemoji@15.1.0/assets/svg/";
+
+/// `😄` -> `{TWEMOJI_BASE_URL}1f604.svg`, the variation selector `U+FE0F` is
+/// not in the file names unless the emoji is a ZWJ sequence
+fn twemoji_url(emoji: &str) -> String {
+    let is_zwj_sequence = emoji.contains('\u{200d}');
+    let code = emoji
+        .chars()
+        .filter(|x| is_zwj_sequence || *x != '\u{fe0f}')
+        .map(|x| format!("{:x}", u32::from(x)))
+        .collect::<Vec<_>>()
+        .join("-");
+
+    format!("{TWEMOJI_BASE_URL}{code}.svg")
+}
+
+/// Give the twemoji images their emoji as `alt` for copy and screen readers,
+/// comrak renders the images from [`MarkdownAst::shortcodes_to_twemoji`] with it in `title`
+fn rewrite_twemoji(html: &str) -> anyhow::Result<String> {
+    let selector = format!("img[src^=\"{TWEMOJI_BASE_URL}\"]");
+
+    let html = lol_html::rewrite_str(
+        html,
+        lol_html::RewriteStrSettings {
+            element_content_handlers: vec![lol_html::element!(selector, |el| {
+                let emoji = el.get_attribute("title").unwrap_or_default();
+                el.remove_attribute("title");
+                el.remove_attribute("loading");
+                el.set_attribute("alt", &emoji)?;
+                el.set_attribute("class", "emoji")?;
+                Ok(())
+            })],
+            ..lol_html::RewriteStrSettings::new()
+        },
+    )?;
+
+    Ok(html)
+}
+
+/// Prepend `base_path` to the absolute internal urls (e.g. `/blog/abc/`) in `html`,
+/// for the sites hosted under a subdirectory. Protocol-relative urls are kept.
+pub fn prefix_urls(html: &str, base_path: &str) -> anyhow::Result<String> {
+    let prefix = |url: &str| -> Option<String> {
+        (url.starts_with('/') && !url.starts_with("//")).then(|| format!("{base_path}{url}"))
+    };
+
+    let html = lol_html::rewrite_str(
+        html,
+        lol_html::RewriteStrSettings {
+            element_content_handlers: vec![lol_html::element!("*", |el| {
+                for attr in ["href", "src", "poster", "action"] {
+                    if let Some(url) = el.get_attribute(attr).and_then(|x| prefix(&x)) {
+                        el.set_attribute(attr, &url)?;
+                    }
+                }
+                if let Some(srcset) = el.get_attribute("srcset") {
+                    let srcset = srcset
+                        .split(',')
+                        .map(|x| prefix(x.trim()).unwrap_or_else(|| x.trim().to_string()))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    el.set_attribute("srcset", &srcset)?;
+                }
+                Ok(())
+            })],
+            ..lol_html::RewriteStrSettings::new()
+        },
+    )?;
+
+    Ok(html)
 }
 
 /// Parse blog file name: `yyyy-mm-dd-blog-slug`
@@ -41,10 +265,183 @@ pub fn parse_blog_file_name(name: &str) -> anyhow::Result<(chrono::NaiveDate, &s
     }
 }
 
+/// Everything before this in the markdown is the excerpt
+const EXCERPT_DELIMITER: &str = "<!-- more -->";
+
+/// Theme for `static/syntax.css` if no `syntax_theme` is configured
+pub const DEFAULT_SYNTAX_THEME: &str = "InspiredGitHub";
+
+/// Check if syntect's default theme set has a theme named `name`
+pub fn has_syntax_theme(name: &str) -> bool {
+    syntect::highlighting::ThemeSet::load_defaults()
+        .themes
+        .contains_key(name)
+}
+
+/// Generate the stylesheet for code highlighted with CSS classes
+pub fn syntax_theme_css(name: &str) -> anyhow::Result<String> {
+    let theme_set = syntect::highlighting::ThemeSet::load_defaults();
+    let theme = theme_set
+        .themes
+        .get(name)
+        .with_context(|| format!("syntax theme `{name}` not found"))?;
+
+    let css =
+        syntect::html::css_for_theme_with_class_style(theme, syntect::html::ClassStyle::Spaced)?;
+
+    Ok(css)
+}
+
+/// Syntect's default syntaxes with the `.sublime-syntax` files in `dir`
+fn load_syntax_set(dir: &Path) -> anyhow::Result<syntect::parsing::SyntaxSet> {
+    let mut builder = syntect::parsing::SyntaxSet::load_defaults_newlines().into_builder();
+    builder
+        .add_from_folder(dir, true)
+        .with_context(|| format!("failed to load syntaxes from {}", dir.display()))?;
+    Ok(builder.build())
+}
+
+/// Code highlighter of the markdown pipeline, built once and shared by all the files
+#[derive(Clone)]
+pub struct Highlighter(Arc<SyntectAdapter>);
+
+impl Highlighter {
+    /// Highlight with the inline styles of the syntect `theme`, or CSS classes if `None`.
+    /// The `.sublime-syntax` files in `syntaxes_dir` are added to syntect's default syntaxes
+    pub fn new(theme: Option<&str>, syntaxes_dir: Option<&Path>) -> anyhow::Result<Self> {
+        let Some(dir) = syntaxes_dir else {
+            return Ok(Self(Arc::new(SyntectAdapter::new(theme))));
+        };
+
+        let syntax_set = load_syntax_set(dir)?;
+        tracing::info!("loaded {} syntaxes", syntax_set.syntaxes().len());
+        let builder = SyntectAdapterBuilder::new().syntax_set(syntax_set);
+        let adapter = match theme {
+            Some(theme) => builder.theme(theme),
+            None => builder.css(),
+        }
+        .build();
+
+        Ok(Self(Arc::new(adapter)))
+    }
+}
+
+impl Default for Highlighter {
+    /// CSS classes with syntect's default syntaxes
+    fn default() -> Self {
+        static DEFAULT: LazyLock<Highlighter> =
+            LazyLock::new(|| Highlighter(Arc::new(SyntectAdapter::new(None))));
+        DEFAULT.clone()
+    }
+}
+
+impl std::fmt::Debug for Highlighter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Highlighter").finish_non_exhaustive()
+    }
+}
+
+/// Options of the markdown pipeline, filled from the site config by the generator
+#[derive(Debug, Clone)]
+pub struct MarkdownOptions {
+    /// Highlighter of the code blocks
+    pub highlighter: Highlighter,
+    /// Rewrite `abc.md` links to `abc`, otherwise to `abc.html`
+    pub pretty_urls: bool,
+    /// Rewrite `abc.md` links to `abc/` with pretty urls
+    pub trailing_slash: bool,
+    /// `permalink` config, links to blog posts are rewritten to it
+    pub permalink: String,
+    /// Heading of the footnotes section
+    pub footnotes_heading: String,
+    /// Symbol of the anchor links on the headings, no anchor links if empty
+    pub heading_anchor: String,
+    /// Levels of the headings with anchor links
+    pub anchor_min_level: u8,
+    pub anchor_max_level: u8,
+    /// Line numbers on all code blocks, otherwise only with `linenos` in the info string
+    pub line_numbers: bool,
+    /// `site_url` config, links to other sites are external
+    pub site_url: String,
+    /// Open external links in a new tab with `rel="noopener noreferrer"`
+    pub external_links: bool,
+    /// Add the `external` class to external links
+    pub external_link_marker: bool,
+    /// Widths of the resized variants of the local images in `srcset`, no `srcset` if empty
+    pub image_widths: Vec<u32>,
+    /// `sizes` of the images with `srcset`
+    pub image_sizes: String,
+    /// Blurred placeholders of the local images as the background
+    pub image_placeholders: bool,
+    /// Comrak extensions to enable or disable over [`default_option`], see [`set_extension`]
+    pub extensions: BTreeMap<String, bool>,
+    /// [`wikilink_key`] of the page names -> page urls, to resolve `[[Some Page]]`
+    pub wikilinks: Arc<BTreeMap<String, String>>,
+    /// Render the emoji shortcodes (e.g. `:smile:`) as twemoji images
+    pub twemoji: bool,
+}
+
+impl MarkdownOptions {
+    /// The options of `config`, the wikilinks are filled once the pages are collected.
+    /// Unknown markdown extensions are dropped with a warning.
+    pub(crate) fn from_config(config: &config::Config, highlighter: Highlighter) -> Self {
+        Self {
+            highlighter,
+            pretty_urls: config.pretty_urls,
+            trailing_slash: config.trailing_slash == my_site_web::TrailingSlash::Always,
+            permalink: config.permalink.clone(),
+            footnotes_heading: config.footnotes_heading.clone(),
+            heading_anchor: config.heading_anchor.clone(),
+            anchor_min_level: config.anchor_min_level,
+            anchor_max_level: config.anchor_max_level,
+            line_numbers: config.code_line_numbers,
+            site_url: config.site_url.clone(),
+            external_links: config.external_links,
+            external_link_marker: config.external_link_marker,
+            image_widths: if config.responsive_images.enabled {
+                config.responsive_images.widths.clone()
+            } else {
+                vec![]
+            },
+            image_sizes: config.responsive_images.sizes.clone(),
+            image_placeholders: config.image_placeholders,
+            twemoji: config.emoji == config::EmojiStyle::Twemoji,
+            extensions: {
+                let mut extensions = config.markdown.clone();
+                extensions.retain(|name, _| {
+                    let known = is_extension(name);
+                    if !known {
+                        tracing::warn!("unknown markdown extension `{name}`, ignored");
+                    }
+                    known
+                });
+                if config.emoji == config::EmojiStyle::Off {
+                    extensions.insert("shortcodes".to_string(), false);
+                }
+                extensions
+            },
+            wikilinks: Arc::default(),
+        }
+    }
+}
+
+impl Default for MarkdownOptions {
+    /// Same as the pages of a site with the default config
+    fn default() -> Self {
+        Self::from_config(&config::Config::default(), Highlighter::default())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Markdown {
     pub meta: MarkdownMeta,
     pub html: String,
+    /// Summary shown on the blog list
+    pub excerpt_html: Option<String>,
+    /// Whether the page needs the math renderer
+    pub has_math: bool,
+    /// Whether the page needs the mermaid diagram renderer
+    pub has_mermaid: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -53,11 +450,40 @@ pub struct MarkdownMeta {
     pub description_md: Option<String>,
     pub description_html: Option<String>,
     pub tags: Vec<String>,
+    pub canonical: Option<String>,
+    pub slug: Option<String>,
+    /// Overrides the site author
+    pub author: Option<String>,
+    /// Name of the series this post belongs to
+    pub series: Option<String>,
+    /// Only built with drafts enabled
+    pub draft: bool,
+    /// Show the comment widget on the blog post, if configured
+    pub comments: bool,
+    /// Overrides the site `lang`
+    pub lang: Option<String>,
+    /// Language -> url of the translations of this page
+    pub translations: BTreeMap<String, String>,
+    /// Overrides the site `title_suffix`
+    pub title_suffix: Option<String>,
+    /// Overrides the site `theme_color`
+    pub theme_color: Option<config::ThemeColor>,
+    /// Body structure of the page
+    pub layout: config::Layout,
+    /// Old urls of this page, redirected to the page
+    pub aliases: Vec<String>,
+    /// Number of words in the text, excluding code blocks
+    pub word_count: usize,
+    /// Extra stylesheets of this page, in document order
+    pub styles: Vec<String>,
+    /// Extra scripts of this page, in document order
+    pub scripts: Vec<String>,
 }
 
 struct MarkdownSource<'a> {
     base_dir: PathBuf,
     file_path: PathBuf,
+    md_options: MarkdownOptions,
 
     content: String,
     arena: Arena<'a>,
@@ -65,7 +491,9 @@ struct MarkdownSource<'a> {
 
 struct MarkdownAst<'a> {
     root: Node<'a>,
+    file_path: &'a Path,
     options: comrak::Options<'static>,
+    md_options: &'a MarkdownOptions,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -74,28 +502,68 @@ struct Frontmatter {
     description: Option<String>,
     #[serde(default)]
     tags: Vec<String>,
+    canonical: Option<String>,
+    slug: Option<String>,
+    author: Option<String>,
+    series: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    comments: Option<bool>,
+    lang: Option<String>,
+    #[serde(default)]
+    translations: BTreeMap<String, String>,
+    title_suffix: Option<String>,
+    theme_color: Option<config::ThemeColor>,
+    layout: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    styles: Vec<String>,
+    #[serde(default)]
+    scripts: Vec<String>,
 }
 
 impl<'a> MarkdownSource<'a> {
-    fn new(base_dir: impl Into<PathBuf>, file_path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+    fn new(
+        base_dir: impl Into<PathBuf>,
+        file_path: impl Into<PathBuf>,
+        md_options: MarkdownOptions,
+    ) -> anyhow::Result<Self> {
         let base_dir = base_dir.into();
         let file_path = file_path.into();
 
         let content = std::fs::read_to_string(base_dir.join(&file_path))?;
-        let arena = Arena::new();
+        Ok(Self::from_content(
+            base_dir, file_path, &content, md_options,
+        ))
+    }
 
-        Ok(Self {
-            base_dir,
-            file_path,
-            content,
-            arena,
-        })
+    fn from_content(
+        base_dir: impl Into<PathBuf>,
+        file_path: impl Into<PathBuf>,
+        content: &str,
+        md_options: MarkdownOptions,
+    ) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+            file_path: file_path.into(),
+            md_options,
+            content: separate_callout_fences(content),
+            arena: Arena::new(),
+        }
     }
 
     fn parse(&'a self) -> MarkdownAst<'a> {
         let options = self.options();
         let root = comrak::parse_document(&self.arena, &self.content, &options);
-        MarkdownAst { root, options }
+        normalize_code_info(root);
+        build_callouts(&self.arena, root, &self.file_path);
+        MarkdownAst {
+            root,
+            file_path: &self.file_path,
+            options,
+            md_options: &self.md_options,
+        }
     }
 
     fn options(&self) -> comrak::Options<'static> {
@@ -104,25 +572,43 @@ impl<'a> MarkdownSource<'a> {
             .parent()
             .map(Path::to_path_buf)
             .unwrap_or_default();
+        let url_style = config::UrlStyle {
+            pretty_urls: self.md_options.pretty_urls,
+            trailing_slash: self.md_options.trailing_slash,
+        };
+        let permalink = self.md_options.permalink.clone();
 
         let link_url_rewriter = move |url: &str| {
-            // if `url` is real url (not a path)
-            if url.contains("://") || url.starts_with("mailto:") {
+            if is_real_url(url) {
                 return url.to_string();
             }
 
             // get path relative to base dir
             let mut p = Path::new("/").join(&dir_path).join(url).normalize();
 
-            // remove `.md` extension
-            if p.extension().and_then(|x| x.to_str()) == Some("md") {
-                p.set_extension("");
+            if p.extension().and_then(|x| x.to_str()) != Some("md") {
+                return p.to_str().unwrap_or_default().to_string();
             }
 
-            p.to_str().unwrap_or_default().to_string()
+            // blog posts follow the permalink, the frontmatter `slug` of
+            // the linked post is unknown here so the file name is used
+            let name = p.file_stem().and_then(|x| x.to_str()).unwrap_or_default();
+            let is_blog = p.parent() == Some(Path::new("/").join(config::BLOG_DIR).as_path());
+            let blog = parse_blog_file_name(name).ok().filter(|_| is_blog);
+            if let Some((date, slug)) = blog {
+                let rel_path = config::blog_permalink(&permalink, date, slug, name);
+                return config::page_link(rel_path, url_style);
+            }
+
+            // remove `.md` extension
+            p.set_extension("");
+            config::page_link(p, url_style)
         };
 
         let mut options = default_option();
+        for (name, &enabled) in &self.md_options.extensions {
+            set_extension(&mut options.extension, name, enabled);
+        }
 
         options.extension.link_url_rewriter = Some(Arc::new(link_url_rewriter));
 
@@ -130,7 +616,335 @@ impl<'a> MarkdownSource<'a> {
     }
 }
 
+impl MarkdownSource<'_> {
+    /// Add `loading="lazy"` and `decoding="async"` to images, the
+    /// `width`/`height` of local images to avoid layout shift, the
+    /// `srcset` of their resized variants, and their placeholders.
+    fn rewrite_images(&self, html: &str) -> anyhow::Result<String> {
+        let dir = self
+            .base_dir
+            .join(self.file_path.parent().unwrap_or(Path::new("")));
+
+        let html = lol_html::rewrite_str(
+            html,
+            lol_html::RewriteStrSettings {
+                element_content_handlers: vec![lol_html::element!("img[src]", |el| {
+                    el.set_attribute("loading", "lazy")?;
+                    el.set_attribute("decoding", "async")?;
+
+                    let src = el.get_attribute("src").unwrap_or_default();
+                    if src.contains("://") || src.starts_with("data:") || src.starts_with("//") {
+                        return Ok(());
+                    }
+
+                    let path = match src.strip_prefix('/') {
+                        Some(src) => self.base_dir.join(src),
+                        None => dir.join(&src),
+                    };
+
+                    let size = match imagesize::size(&path) {
+                        Ok(size) => size,
+                        Err(err) => {
+                            tracing::warn!("failed to get image size: {}: {err}", path.display());
+                            return Ok(());
+                        }
+                    };
+
+                    if !el.has_attribute("width") && !el.has_attribute("height") {
+                        el.set_attribute("width", &size.width.to_string())?;
+                        el.set_attribute("height", &size.height.to_string())?;
+                    }
+
+                    let width = size.width as u32;
+                    let has_variants = images::variant_widths(&self.md_options.image_widths, width)
+                        .next()
+                        .is_some();
+                    if has_variants && images::is_resizable(&path) && !el.has_attribute("srcset") {
+                        // the original is the fallback and the widest candidate
+                        let srcset = images::variant_widths(&self.md_options.image_widths, width)
+                            .map(|x| format!("{} {x}w", images::variant_url(&src, x)))
+                            .chain([format!("{src} {width}w")])
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        el.set_attribute("srcset", &srcset)?;
+                        el.set_attribute("sizes", &self.md_options.image_sizes)?;
+                    }
+
+                    if self.md_options.image_placeholders && images::is_resizable(&path) {
+                        match images::placeholder(&path) {
+                            Ok(Some(data_url)) => {
+                                let style = el.get_attribute("style").unwrap_or_default();
+                                let style = format!(
+                                    "background-image:url({data_url});background-size:cover;{style}"
+                                );
+                                el.set_attribute("style", &style)?;
+                            }
+                            Ok(None) => {}
+                            Err(err) => tracing::warn!("{err:#}"),
+                        }
+                    }
+
+                    Ok(())
+                })],
+                ..lol_html::RewriteStrSettings::new()
+            },
+        )?;
+
+        Ok(html)
+    }
+
+    /// Label the footnotes section with a heading, and make the footnote
+    /// references refer to it for screen readers.
+    fn rewrite_footnotes(&self, html: &str) -> anyhow::Result<String> {
+        let heading = self
+            .md_options
+            .footnotes_heading
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+
+        let html = lol_html::rewrite_str(
+            html,
+            lol_html::RewriteStrSettings {
+                element_content_handlers: vec![
+                    lol_html::element!("a[data-footnote-ref]", |el| {
+                        el.set_attribute("aria-describedby", "footnote-label")?;
+                        Ok(())
+                    }),
+                    lol_html::element!("a[data-footnote-backref]", |el| {
+                        if !el.has_attribute("aria-label") {
+                            el.set_attribute("aria-label", "Back to content")?;
+                        }
+                        Ok(())
+                    }),
+                    lol_html::element!("section[data-footnotes]", |el| {
+                        el.set_attribute("aria-labelledby", "footnote-label")?;
+                        el.prepend(
+                            &format!(r#"<h2 id="footnote-label">{heading}</h2>"#),
+                            lol_html::html_content::ContentType::Html,
+                        );
+                        Ok(())
+                    }),
+                ],
+                ..lol_html::RewriteStrSettings::new()
+            },
+        )?;
+
+        Ok(html)
+    }
+
+    /// Wrap each line of the code blocks with `<span class="line">` for the line
+    /// numbers, if the block has `linenos` in the info string or it's enabled for all.
+    fn rewrite_code_lines(&self, html: &str) -> String {
+        map_pre_blocks(html, |block| self.rewrite_code_block_lines(block))
+    }
+
+    /// `block` is `<pre><code>...</code>`
+    fn rewrite_code_block_lines(&self, block: &str) -> String {
+        let Some(code_start) = block.find("<code") else {
+            return block.to_string();
+        };
+        let Some(code_end) = block.rfind("</code>") else {
+            return block.to_string();
+        };
+        let Some(open_end) = block[code_start..code_end].find('>') else {
+            return block.to_string();
+        };
+        let open_end = code_start + open_end + 1;
+
+        let open_tag = &block[code_start..open_end];
+        // math is rendered by KaTeX
+        if open_tag.contains("data-math-style") {
+            return block.to_string();
+        }
+
+        let meta = attribute_value(open_tag, "data-meta").unwrap_or_default();
+        let line_numbers =
+            self.md_options.line_numbers || meta.split_whitespace().any(|x| x == "linenos");
+        let highlighted = self.highlighted_lines(meta);
+        if !line_numbers && highlighted.is_empty() {
+            return block.to_string();
+        }
+
+        let open_tag = if line_numbers {
+            open_tag.replacen("<code", "<code data-line-numbers", 1)
+        } else {
+            open_tag.to_string()
+        };
+
+        format!(
+            "{}{}{}{}",
+            &block[..code_start],
+            open_tag,
+            wrap_code_lines(&block[open_end..code_end], &highlighted),
+            &block[code_end..],
+        )
+    }
+
+    /// Lines in the `{2,5-7}` of the info string, invalid ranges are ignored with a warning
+    fn highlighted_lines(&self, meta: &str) -> Vec<RangeInclusive<usize>> {
+        let Some(spec) = meta
+            .split_whitespace()
+            .find_map(|x| x.strip_prefix('{')?.strip_suffix('}'))
+        else {
+            return Vec::new();
+        };
+
+        let parse = |range: &str| -> Option<RangeInclusive<usize>> {
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+            (1 <= start && start <= end).then_some(start..=end)
+        };
+
+        let mut ret = Vec::new();
+        for range in spec.split(',').filter(|x| !x.trim().is_empty()) {
+            match parse(range) {
+                Some(range) => ret.push(range),
+                None => tracing::warn!(
+                    "invalid highlighted lines `{range}` of a code block in {}, ignored",
+                    self.file_path.display()
+                ),
+            }
+        }
+
+        ret
+    }
+
+    /// Wrap code blocks with a container that has a copy button.
+    fn rewrite_code_blocks(&self, html: &str) -> anyhow::Result<String> {
+        use lol_html::html_content::ContentType;
+
+        let html = lol_html::rewrite_str(
+            html,
+            lol_html::RewriteStrSettings {
+                element_content_handlers: vec![lol_html::element!("pre:not(.mermaid)", |el| {
+                    el.before(
+                        r#"<div class="code-block"><button class="copy" type="button">copy</button>"#,
+                        ContentType::Html,
+                    );
+                    el.after("</div>", ContentType::Html);
+                    Ok(())
+                })],
+                ..lol_html::RewriteStrSettings::new()
+            },
+        )?;
+
+        Ok(html)
+    }
+
+    /// Fill the empty anchors comrak emits for `header_ids` on the headings of
+    /// `anchor_min_level..=anchor_max_level`, so readers can link to the sections.
+    /// comrak only prefixes the `id`, so the `href` is pointed to it.
+    fn rewrite_heading_anchors(&self, html: &str) -> anyhow::Result<String> {
+        let anchor = &self.md_options.heading_anchor;
+        let levels = self.md_options.anchor_min_level..=self.md_options.anchor_max_level;
+        if anchor.is_empty() || levels.is_empty() {
+            return Ok(html.to_string());
+        }
+
+        let selector = levels
+            .map(|level| format!("h{level} > a.anchor"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let html = lol_html::rewrite_str(
+            html,
+            lol_html::RewriteStrSettings {
+                element_content_handlers: vec![lol_html::element!(selector, |el| {
+                    el.remove_attribute("inert");
+                    if let Some(id) = el.get_attribute("id") {
+                        el.set_attribute("href", &format!("#{id}"))?;
+                    }
+                    el.set_inner_content(anchor, lol_html::html_content::ContentType::Text);
+                    Ok(())
+                })],
+                ..lol_html::RewriteStrSettings::new()
+            },
+        )?;
+
+        Ok(html)
+    }
+
+    /// Open the external links in a new tab without leaking the page, and
+    /// mark them with the `external` class if enabled.
+    fn rewrite_external_links(&self, html: &str) -> anyhow::Result<String> {
+        if !self.md_options.external_links {
+            return Ok(html.to_string());
+        }
+
+        let html = lol_html::rewrite_str(
+            html,
+            lol_html::RewriteStrSettings {
+                element_content_handlers: vec![lol_html::element!("a[href]", |el| {
+                    let href = el.get_attribute("href").unwrap_or_default();
+                    if !is_external_url(&href, &self.md_options.site_url) {
+                        return Ok(());
+                    }
+
+                    el.set_attribute("target", "_blank")?;
+                    el.set_attribute("rel", "noopener noreferrer")?;
+                    if self.md_options.external_link_marker {
+                        let class = match el.get_attribute("class") {
+                            Some(class) => format!("{class} external"),
+                            None => "external".to_string(),
+                        };
+                        el.set_attribute("class", &class)?;
+                    }
+                    Ok(())
+                })],
+                ..lol_html::RewriteStrSettings::new()
+            },
+        )?;
+
+        Ok(html)
+    }
+}
+
 impl MarkdownAst<'_> {
+    /// Point the `[[wikilinks]]` to the pages by their names, the displayed text
+    /// is the target name unless given with `[[target|text]]`
+    fn resolve_wikilinks(&self) {
+        if self.md_options.wikilinks.is_empty() {
+            return;
+        }
+
+        for node in self.root.descendants() {
+            let mut data = node.data_mut();
+            let NodeValue::WikiLink(link) = &mut data.value else {
+                continue;
+            };
+
+            let (target, anchor) = match link.url.split_once('#') {
+                Some((target, anchor)) => (target, Some(anchor)),
+                None => (link.url.as_str(), None),
+            };
+
+            // the target may have been resolved against the page dir like other links
+            let url = self
+                .md_options
+                .wikilinks
+                .get(&wikilink_key(target))
+                .or_else(|| {
+                    let name = target.rsplit('/').next().unwrap_or(target);
+                    self.md_options.wikilinks.get(&wikilink_key(name))
+                });
+            let Some(url) = url else {
+                tracing::warn!(
+                    "{}: unresolved wikilink `[[{}]]`",
+                    self.file_path.display(),
+                    link.url
+                );
+                continue;
+            };
+
+            let url = match anchor {
+                Some(anchor) => format!("{url}#{anchor}"),
+                None => url.clone(),
+            };
+            link.url = url;
+        }
+    }
+
     fn to_meta(&self) -> anyhow::Result<MarkdownMeta> {
         let frontmatter = self
             .get_frontmatter()
@@ -139,34 +953,140 @@ impl MarkdownAst<'_> {
         let title = frontmatter
             .title
             .or_else(|| self.find_title())
-            .context("cannot get title")?;
+            .with_context(|| {
+                format!(
+                    "cannot get title of {}, either frontmatter `title:` or an h1 is required",
+                    self.file_path.display()
+                )
+            })?;
 
         let description_md = frontmatter.description.or_else(|| self.find_description());
         let description_html = description_md
             .as_deref()
             .map(|md| comrak::markdown_to_html(md, &self.options));
 
+        let layout = match frontmatter.layout.as_deref() {
+            None => config::Layout::Default,
+            Some(name) => config::Layout::from_name(name).unwrap_or_else(|| {
+                tracing::warn!(
+                    "unknown layout `{name}` in {}, fallback to default",
+                    self.file_path.display()
+                );
+                config::Layout::Default
+            }),
+        };
+
         Ok(MarkdownMeta {
             title,
             description_md,
             description_html,
             tags: frontmatter.tags,
+            canonical: frontmatter.canonical,
+            slug: frontmatter.slug,
+            author: frontmatter.author,
+            series: frontmatter.series,
+            draft: frontmatter.draft,
+            comments: frontmatter.comments.unwrap_or(true),
+            lang: frontmatter.lang,
+            translations: frontmatter.translations,
+            title_suffix: frontmatter.title_suffix,
+            theme_color: frontmatter.theme_color,
+            layout,
+            aliases: frontmatter.aliases,
+            word_count: self.word_count(),
+            styles: frontmatter.styles,
+            scripts: frontmatter.scripts,
         })
     }
 
     fn to_html(&self) -> anyhow::Result<String> {
+        self.nodes_to_html([self.root])
+    }
+
+    /// Html of everything before the `<!-- more -->` delimiter,
+    /// or the first paragraph if there is no delimiter.
+    fn to_excerpt_html(&self) -> anyhow::Result<Option<String>> {
+        let is_delimiter = |node: Node<'_>| match &node.data().value {
+            NodeValue::HtmlBlock(html) => html.literal.trim() == EXCERPT_DELIMITER,
+            _ => false,
+        };
+
+        if self.root.children().any(is_delimiter) {
+            let nodes = self
+                .root
+                .children()
+                .take_while(|&node| !is_delimiter(node))
+                .filter(|node| !matches!(node.data().value, NodeValue::FrontMatter(_)));
+
+            return self.nodes_to_html(nodes).map(Some);
+        }
+
+        Ok(self
+            .find_description()
+            .map(|md| comrak::markdown_to_html(&md, &self.options)))
+    }
+
+    fn nodes_to_html<'n>(
+        &self,
+        nodes: impl IntoIterator<Item = Node<'n>>,
+    ) -> anyhow::Result<String> {
         let mut ret = String::new();
 
-        // code highlight
-        let adapter = SyntectAdapter::new(None);
         let mut plugins = comrak::options::Plugins::default();
-        plugins.render.codefence_syntax_highlighter = Some(&adapter);
+        plugins.render.codefence_syntax_highlighter = Some(&*self.md_options.highlighter.0);
 
-        comrak::format_html_with_plugins(self.root, &self.options, &mut ret, &plugins)?;
+        for node in nodes {
+            comrak::format_html_with_plugins(node, &self.options, &mut ret, &plugins)?;
+        }
 
         Ok(ret)
     }
 
+    /// Turn the emoji of the shortcodes into images, see [`rewrite_twemoji`]
+    fn shortcodes_to_twemoji(&self) {
+        for node in self.root.descendants() {
+            let mut data = node.data_mut();
+            let NodeValue::ShortCode(shortcode) = &data.value else {
+                continue;
+            };
+
+            // the emoji is kept in the title until `rewrite_twemoji` moves it to `alt`
+            let link = comrak::nodes::NodeLink {
+                url: twemoji_url(&shortcode.emoji),
+                title: shortcode.emoji.clone(),
+            };
+            data.value = NodeValue::Image(Box::new(link));
+        }
+    }
+
+    fn has_math(&self) -> bool {
+        self.root
+            .descendants()
+            .any(|node| matches!(node.data().value, NodeValue::Math(_)))
+    }
+
+    fn has_mermaid(&self) -> bool {
+        self.root
+            .descendants()
+            .any(|node| match &node.data().value {
+                NodeValue::CodeBlock(block) => {
+                    block.info.split_whitespace().next() == Some("mermaid")
+                }
+                _ => false,
+            })
+    }
+
+    fn word_count(&self) -> usize {
+        self.root
+            .descendants()
+            .map(|node| match &node.data().value {
+                NodeValue::Text(text) => text.split_whitespace().count(),
+                NodeValue::Code(code) => code.literal.split_whitespace().count(),
+                _ => 0,
+            })
+            .sum()
+    }
+
     fn find_first_node<T>(&self, find: impl FnMut(Node<'_>) -> Option<T>) -> Option<T> {
         self.root.descendants().find_map(find)
     }
@@ -218,6 +1138,332 @@ impl MarkdownAst<'_> {
     }
 }
 
+/// Types of the `:::type Title` callouts, also their CSS class `callout-{type}`
+const CALLOUT_TYPES: &[&str] = &["note", "tip", "warning", "danger"];
+
+/// Put the `:::type Title` and `:::` lines of callouts in their own paragraphs,
+/// turned into `<div class="callout">` by [`build_callouts`]. The lines in code
+/// blocks, html blocks and the frontmatter are kept as is, the indentation of the
+/// others too, so the callouts in list items stay in them.
+fn separate_callout_fences(content: &str) -> String {
+    if !content.lines().any(|x| x.trim_start().starts_with(":::")) {
+        return content.to_string();
+    }
+
+    // let comrak tell the lines which aren't markdown text
+    let arena = Arena::new();
+    let root = comrak::parse_document(&arena, content, &default_option());
+    let literal_lines: Vec<RangeInclusive<usize>> = root
+        .descendants()
+        .filter(|node| {
+            matches!(
+                node.data().value,
+                NodeValue::CodeBlock(_) | NodeValue::HtmlBlock(_) | NodeValue::FrontMatter(_)
+            )
+        })
+        .map(|node| {
+            let pos = node.data().sourcepos;
+            pos.start.line..=pos.end.line
+        })
+        .collect();
+
+    let mut ret = String::with_capacity(content.len());
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let is_literal = literal_lines.iter().any(|x| x.contains(&(i + 1)));
+        if is_literal || !line.trim_start().starts_with(":::") {
+            ret.push_str(line);
+            continue;
+        }
+
+        ret.push('\n');
+        ret.push_str(line.trim_end());
+        ret.push_str("\n\n");
+    }
+
+    ret
+}
+
+/// A paragraph of a `:::type Title` or `:::` line
+enum CalloutMarker {
+    Open(String),
+    Close,
+}
+
+impl CalloutMarker {
+    fn of(node: Node<'_>, file_path: &Path) -> Option<Self> {
+        if !matches!(node.data().value, NodeValue::Paragraph) {
+            return None;
+        }
+        let first = node.first_child()?;
+        let NodeValue::Text(text) = &first.data().value else {
+            return None;
+        };
+        let marker = text.strip_prefix(":::")?;
+
+        if marker.trim().is_empty() && first.next_sibling().is_none() {
+            return Some(Self::Close);
+        }
+
+        let kind = marker.split_whitespace().next().unwrap_or_default();
+        let kind = kind.to_lowercase();
+        if !CALLOUT_TYPES.contains(&kind.as_str()) {
+            tracing::warn!(
+                "unknown callout type `{kind}` in {}, expected one of: {CALLOUT_TYPES:?}",
+                file_path.display()
+            );
+            return None;
+        }
+
+        Some(Self::Open(kind))
+    }
+}
+
+/// `:::warning Title` ... `:::` paragraphs -> `<div class="callout callout-warning">`
+/// with the title, the type is the title if it's omitted. The markers are paired in
+/// the same parent, the unpaired ones are left as text with a warning.
+fn build_callouts<'a>(arena: &'a Arena<'a>, root: Node<'a>, file_path: &Path) {
+    for parent in root.descendants() {
+        let mut opened = Vec::new();
+        let mut callouts = Vec::new();
+
+        for node in parent.children() {
+            match CalloutMarker::of(node, file_path) {
+                Some(CalloutMarker::Open(kind)) => opened.push((node, kind)),
+                Some(CalloutMarker::Close) => match opened.pop() {
+                    Some((open, kind)) => callouts.push((open, kind, node)),
+                    None => tracing::warn!(
+                        "unmatched callout `:::` at line {} in {}",
+                        node.data().sourcepos.start.line,
+                        file_path.display()
+                    ),
+                },
+                None => {}
+            }
+        }
+        for (node, _) in opened {
+            tracing::warn!(
+                "unclosed callout at line {} in {}",
+                node.data().sourcepos.start.line,
+                file_path.display()
+            );
+        }
+
+        for (open, kind, close) in callouts {
+            open_callout(arena, open, &kind);
+            close.data_mut().value = NodeValue::Raw("</div>\n".to_string());
+            for child in close.children().collect::<Vec<_>>() {
+                child.detach();
+            }
+        }
+    }
+}
+
+/// Turn the `:::kind Title` paragraph into the start of the callout, the inlines
+/// of the title are kept to render them as usual
+fn open_callout<'a>(arena: &'a Arena<'a>, node: Node<'a>, kind: &str) {
+    let first = node.first_child().expect("callout marker has a text");
+    let title = {
+        let mut data = first.data_mut();
+        let NodeValue::Text(text) = &mut data.value else {
+            unreachable!("callout marker starts with a text")
+        };
+        let title = text.trim_start_matches(':').trim_start();
+        let title = title.get(kind.len()..).unwrap_or_default().trim_start();
+        let title = title.to_string();
+        *text = title.clone().into();
+        title
+    };
+
+    if title.is_empty() && first.next_sibling().is_none() {
+        let mut chars = kind.chars();
+        let title = chars
+            .next()
+            .map(|x| x.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default();
+        first.data_mut().value = NodeValue::Text(title.into());
+    }
+
+    node.data_mut().value = NodeValue::Raw(format!(
+        "<div class=\"callout callout-{kind}\">\n<p class=\"callout-title\">"
+    ));
+    let end = NodeValue::Raw("</p>\n".to_string());
+    node.append(arena.alloc(comrak::nodes::AstNode::from(end)));
+}
+
+/// Replace each `<pre>...` (without `</pre>`) in `html` with `f`
+fn map_pre_blocks(html: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let mut ret = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(pre_start) = rest.find("<pre") {
+        let Some(pre_end) = rest[pre_start..].find("</pre>").map(|i| pre_start + i) else {
+            break;
+        };
+
+        ret.push_str(&rest[..pre_start]);
+        ret.push_str(&f(&rest[pre_start..pre_end]));
+        rest = &rest[pre_end..];
+    }
+    ret.push_str(rest);
+
+    ret
+}
+
+/// Turn the ```` ```mermaid ```` code blocks into `<pre class="mermaid">` with the
+/// source only, the spans of the highlighter are dropped, rendered by mermaid.js
+fn rewrite_mermaid(html: &str) -> String {
+    map_pre_blocks(html, |block| {
+        let Some(code_start) = block.find("<code") else {
+            return block.to_string();
+        };
+        if !block[code_start..].contains(r#"class="language-mermaid""#) {
+            return block.to_string();
+        }
+
+        // the text is still escaped
+        let mut source = String::new();
+        let mut in_tag = false;
+        for c in block[code_start..].chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                c if !in_tag => source.push(c),
+                _ => {}
+            }
+        }
+
+        format!(r#"<pre class="mermaid">{source}"#)
+    })
+}
+
+/// `rust,linenos` -> `rust linenos`, comrak takes the info string up to the
+/// first space as the language, the rest is rendered as `data-meta`
+fn normalize_code_info(root: Node<'_>) {
+    for node in root.descendants() {
+        if let NodeValue::CodeBlock(block) = &mut node.data_mut().value {
+            let (lang, rest) = block
+                .info
+                .split_once(' ')
+                .unwrap_or((block.info.as_str(), ""));
+            let Some((lang, flags)) = lang.split_once(',') else {
+                continue;
+            };
+
+            let info = format!("{lang} {} {rest}", flags.replace(',', " "));
+            block.info = info.trim().to_string();
+        }
+    }
+}
+
+/// Value of the double-quoted `name` attribute in the opening tag, still escaped
+fn attribute_value<'t>(tag: &'t str, name: &str) -> Option<&'t str> {
+    let start = tag.find(&format!(" {name}=\""))? + name.len() + 3;
+    let len = tag[start..].find('"')?;
+    Some(&tag[start..start + len])
+}
+
+/// Wrap each line of the highlighted code with `<span class="line">`, the spans
+/// of syntect open across lines are closed and reopened in each line.
+///
+/// Lines in `highlighted` (1-based) also get the `highlighted` class.
+fn wrap_code_lines(code: &str, highlighted: &[RangeInclusive<usize>]) -> String {
+    let line_start = |line: usize| {
+        if highlighted.iter().any(|x| x.contains(&line)) {
+            r#"<span class="line highlighted">"#
+        } else {
+            r#"<span class="line">"#
+        }
+    };
+
+    // the last newline is in the spans of syntect, e.g. `}\n</span>`,
+    // it ends the last line instead of starting an empty one
+    let body = code.trim_end_matches("</span>");
+    let (code, trailing_newline) = match body.strip_suffix('\n') {
+        Some(body) => (format!("{body}{}", &code[body.len() + 1..]), true),
+        None => (code.to_string(), false),
+    };
+
+    let mut line = 1;
+    let mut ret = String::from(line_start(line));
+    let mut open_tags: Vec<&str> = Vec::new();
+    let mut rest = code.as_str();
+
+    while let Some(i) = rest.find(['<', '\n']) {
+        ret.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if let Some(after) = rest.strip_prefix('\n') {
+            // syntect only emits `<span>`
+            for _ in &open_tags {
+                ret.push_str("</span>");
+            }
+            ret.push_str("</span>\n");
+            line += 1;
+            ret.push_str(line_start(line));
+            for tag in &open_tags {
+                ret.push_str(tag);
+            }
+            rest = after;
+        } else {
+            let end = rest.find('>').map_or(rest.len(), |x| x + 1);
+            let tag = &rest[..end];
+            if tag.starts_with("</") {
+                open_tags.pop();
+            } else if !tag.ends_with("/>") {
+                open_tags.push(tag);
+            }
+            ret.push_str(tag);
+            rest = &rest[end..];
+        }
+    }
+    ret.push_str(rest);
+    ret.push_str("</span>");
+    if trailing_newline {
+        ret.push('\n');
+    }
+
+    ret
+}
+
+/// If `name` is a comrak extension of [`set_extension`]
+pub fn is_extension(name: &str) -> bool {
+    set_extension(&mut comrak::options::Extension::default(), name, true)
+}
+
+/// Enable or disable the comrak extension by its name, returns `false` if it's unknown
+pub fn set_extension(
+    extension: &mut comrak::options::Extension,
+    name: &str,
+    enabled: bool,
+) -> bool {
+    let flag = match name {
+        "strikethrough" => &mut extension.strikethrough,
+        "tagfilter" => &mut extension.tagfilter,
+        "table" => &mut extension.table,
+        "autolink" => &mut extension.autolink,
+        "tasklist" => &mut extension.tasklist,
+        "superscript" => &mut extension.superscript,
+        "subscript" => &mut extension.subscript,
+        "footnotes" => &mut extension.footnotes,
+        "description_lists" => &mut extension.description_lists,
+        "multiline_block_quotes" => &mut extension.multiline_block_quotes,
+        "alerts" => &mut extension.alerts,
+        "math_dollars" => &mut extension.math_dollars,
+        "math_code" => &mut extension.math_code,
+        "shortcodes" => &mut extension.shortcodes,
+        // `[[target|title]]`
+        "wikilinks" => &mut extension.wikilinks_title_after_pipe,
+        "underline" => &mut extension.underline,
+        "spoiler" => &mut extension.spoiler,
+        "greentext" => &mut extension.greentext,
+        "cjk_friendly_emphasis" => &mut extension.cjk_friendly_emphasis,
+        _ => return false,
+    };
+
+    *flag = enabled;
+    true
+}
+
 pub fn default_option() -> comrak::Options<'static> {
     let extension = comrak::options::Extension {
         strikethrough: true,
@@ -242,6 +1488,8 @@ pub fn default_option() -> comrak::Options<'static> {
     };
     let render = comrak::options::Render {
         experimental_minimize_commonmark: true,
+        // the flags after the language, e.g. `linenos`
+        full_info_string: true,
         ..Default::default()
     };
 
@@ -251,3 +1499,288 @@ pub fn default_option() -> comrak::Options<'static> {
         render,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Inner html of the first `<hN>` element
+    fn heading(html: &str, level: u8) -> &str {
+        let start = html.find(&format!("<h{level}")).expect("heading not found");
+        let end = html[start..].find(&format!("</h{level}>")).unwrap() + start;
+        &html[start..end]
+    }
+
+    /// Html of the markdown `body` under a title
+    fn render_body(body: &str) -> String {
+        render_markdown_str(&format!("# t\n\n{body}"), &Default::default())
+            .unwrap()
+            .html
+    }
+
+    /// Value of the first `name` attribute in `html`
+    fn attribute<'a>(html: &'a str, name: &str) -> Option<&'a str> {
+        let start = html.find(&format!(" {name}=\""))? + name.len() + 3;
+        let end = html[start..].find('"')? + start;
+        Some(&html[start..end])
+    }
+
+    #[test]
+    fn external_urls() {
+        let site_url = "https://example.com/";
+        assert!(!is_external_url("https://example.com", site_url));
+        assert!(!is_external_url("https://example.com/blog/", site_url));
+        assert!(!is_external_url("https://example.com?q=1", site_url));
+        assert!(!is_external_url("mailto:a@example.com", site_url));
+        assert!(is_external_url("https://example.com.evil/", site_url));
+        assert!(is_external_url("https://example.community/", site_url));
+        assert!(is_external_url("https://other.com/", site_url));
+        assert!(is_external_url("https://example.com/", ""));
+    }
+
+    #[test]
+    fn heading_anchors_in_level_range() {
+        let md_options = MarkdownOptions {
+            heading_anchor: "#".to_string(),
+            anchor_min_level: 2,
+            anchor_max_level: 3,
+            ..Default::default()
+        };
+        let md = render_markdown_str("# h1\n\n## h2\n\n### h3\n\n#### h4\n", &md_options).unwrap();
+
+        assert!(!heading(&md.html, 1).contains(">#</a>"));
+        assert!(heading(&md.html, 2).contains(">#</a>"));
+        assert!(heading(&md.html, 3).contains(">#</a>"));
+        assert!(!heading(&md.html, 4).contains(">#</a>"));
+        // the headings out of the range can still be linked to
+        assert!(heading(&md.html, 4).contains("id=\"heading-h4\""));
+    }
+
+    #[test]
+    fn heading_anchors_link_to_their_ids() {
+        let md =
+            render_markdown_str("# a\n\n## Sec one\n\n### Sec two\n", &Default::default()).unwrap();
+
+        for level in [2, 3] {
+            let heading = heading(&md.html, level);
+            let href = attribute(heading, "href").expect("no anchor href");
+            let id = attribute(heading, "id").expect("no anchor id");
+            assert!(id.starts_with("heading-"), "{heading}");
+            assert_eq!(href, format!("#{id}"), "{heading}");
+        }
+    }
+
+    #[test]
+    fn callouts_with_nested_markdown() {
+        let html = render_body(
+            ":::warning Be *careful*\nSome **bold** text\n- one\n- two\n\n:::tip\ninner\n:::\n:::\n\nafter\n",
+        );
+
+        let start = html.find(r#"<div class="callout callout-warning">"#);
+        let end = html.rfind("</div>");
+        let callout = &html[start.expect(&html)..end.expect(&html)];
+        let title = r#"<p class="callout-title">Be <em>careful</em></p>"#;
+        assert!(callout.contains(title), "{html}");
+        assert!(callout.contains("<strong>bold</strong>"), "{html}");
+        assert!(callout.contains("<li>two</li>\n</ul>"), "{html}");
+        assert!(callout.contains(r#"<div class="callout callout-tip">"#));
+        assert!(callout.contains(r#"<p class="callout-title">Tip</p>"#));
+        assert_eq!(html.matches("<div").count(), html.matches("</div>").count());
+        assert!(html[end.unwrap()..].contains("<p>after</p>"), "{html}");
+        assert!(!html.contains(":::"), "{html}");
+
+        // the comrak alerts still work
+        let html = render_body("> [!NOTE]\n> text\n");
+        assert!(html.contains("markdown-alert-note"), "{html}");
+    }
+
+    #[test]
+    fn callout_markers_in_code_are_kept() {
+        let html =
+            render_body("````\n:::tip\n```\n:::\n```\n````\n\ntext\n\n    :::tip\n    code\n");
+        assert!(!html.contains("callout"), "{html}");
+        assert!(html.contains(":::tip\n```\n:::\n```"), "{html}");
+        assert!(html.contains(":::tip\ncode"), "{html}");
+    }
+
+    #[test]
+    fn callouts_in_list_items() {
+        let html = render_body("- one\n  :::note\n  inside\n  :::\n- two\n");
+        assert_eq!(html.matches("<ul>").count(), 1, "{html}");
+
+        let item = &html[html.find("<li>").unwrap()..html.find("</li>").unwrap()];
+        assert!(
+            item.contains(r#"<div class="callout callout-note">"#),
+            "{html}"
+        );
+        assert!(item.contains("</div>"), "{html}");
+    }
+
+    #[test]
+    fn unbalanced_callout_markers() {
+        let html = render_body("text\n\n:::\n");
+        assert!(!html.contains("</div>"), "{html}");
+        assert!(html.contains("<p>:::</p>"), "{html}");
+
+        let html = render_body(":::note\n\ntext\n");
+        assert!(!html.contains("<div"), "{html}");
+        assert!(html.contains("<p>:::note</p>"), "{html}");
+
+        // the close is in another parent than the open
+        let html = render_body(":::note\n\n> :::\n");
+        assert!(!html.contains("<div"), "{html}");
+
+        let html = render_body(":::unknown\ntext\n:::\n");
+        assert!(!html.contains("<div"), "{html}");
+    }
+
+    /// Classes of the `<span class="line">` of the code lines
+    fn line_classes(html: &str) -> Vec<&str> {
+        html.split(r#"<span class="line"#)
+            .skip(1)
+            .map(|x| x[..x.find('"').unwrap()].trim())
+            .collect()
+    }
+
+    const CODE: &str = "fn main() {\n    let a = 1;\n    let b = 2;\n}\n";
+
+    #[test]
+    fn code_line_numbers() {
+        let html = render_body(&format!("```rust\n{CODE}```\n"));
+        assert!(line_classes(&html).is_empty(), "{html}");
+        assert!(!html.contains("data-line-numbers"), "{html}");
+
+        let html = render_body(&format!("```rust,linenos\n{CODE}```\n"));
+        assert!(html.contains("<code data-line-numbers"), "{html}");
+        assert_eq!(line_classes(&html), ["", "", "", ""], "{html}");
+        assert!(!html.contains(r#"<span class="line"></span>"#), "{html}");
+
+        let md_options = MarkdownOptions {
+            line_numbers: true,
+            ..Default::default()
+        };
+        let md = render_markdown_str(&format!("# t\n\n```\n{CODE}```\n"), &md_options).unwrap();
+        assert!(md.html.contains("<code data-line-numbers"), "{}", md.html);
+        assert_eq!(line_classes(&md.html).len(), 4, "{}", md.html);
+    }
+
+    #[test]
+    fn parse_highlighted_lines() {
+        let source = MarkdownSource::from_content("", STR_INPUT_PATH, "", Default::default());
+
+        assert_eq!(source.highlighted_lines("{2,5-7}"), [2..=2, 5..=7]);
+        assert_eq!(source.highlighted_lines("linenos {3} title"), [3..=3]);
+        assert_eq!(source.highlighted_lines("{1,}"), [1..=1]);
+        assert!(source.highlighted_lines("").is_empty());
+        assert!(source.highlighted_lines("{}").is_empty());
+        // the malformed ranges are ignored, the others are kept
+        assert_eq!(source.highlighted_lines("{0,3-1,x,2-,-4,4-5}"), [4..=5]);
+    }
+
+    #[test]
+    fn highlight_code_lines() {
+        let html = render_body(&format!("```rust {{2,4}}\n{CODE}```\n"));
+        assert!(!html.contains("data-line-numbers"), "{html}");
+        assert_eq!(
+            line_classes(&html),
+            ["", "highlighted", "", "highlighted"],
+            "{html}"
+        );
+
+        let html = render_body(&format!("```rust,linenos {{1-2,9}}\n{CODE}```\n"));
+        assert!(html.contains("<code data-line-numbers"), "{html}");
+        assert_eq!(
+            line_classes(&html),
+            ["highlighted", "highlighted", "", ""],
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn prefix_internal_urls() {
+        let html = r#"<a href="/blog/abc/">a</a><a href="//cdn.example.com/x.js">b</a><a href="https://example.com/">c</a><img src="/static/a.png" srcset="/static/a-480.png 480w, /static/a-960.png 960w">"#;
+
+        let prefixed = prefix_urls(html, "/notes").unwrap();
+        assert!(prefixed.contains(r#"href="/notes/blog/abc/""#));
+        assert!(prefixed.contains(r#"href="//cdn.example.com/x.js""#));
+        assert!(prefixed.contains(r#"href="https://example.com/""#));
+        assert!(prefixed.contains(r#"src="/notes/static/a.png""#));
+        assert!(prefixed.contains("/notes/static/a-480.png 480w, /notes/static/a-960.png 960w"));
+
+        assert_eq!(prefix_urls(html, "").unwrap(), html);
+    }
+
+    #[test]
+    fn missing_title_error() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("notes")).unwrap();
+        std::fs::write(dir.path().join("notes/abc.md"), "## not a title\n\ntext\n").unwrap();
+
+        let err = read_md(dir.path(), "notes/abc.md", &MarkdownOptions::default()).unwrap_err();
+        let message = format!("{err:#}");
+        assert!(message.contains("notes/abc.md"), "{message}");
+        assert!(
+            message.contains("either frontmatter `title:` or an h1 is required"),
+            "{message}"
+        );
+
+        std::fs::write(
+            dir.path().join("notes/abc.md"),
+            "---\ntitle: abc\n---\ntext\n",
+        )
+        .unwrap();
+        let meta = read_meta(dir.path(), "notes/abc.md", &MarkdownOptions::default()).unwrap();
+        assert_eq!(meta.title, "abc");
+    }
+
+    #[test]
+    fn default_options_of_the_default_config() {
+        let md_options = MarkdownOptions::default();
+        assert_eq!(md_options.footnotes_heading, "Notes");
+        assert_eq!(md_options.heading_anchor, "#");
+        assert_eq!(
+            md_options.anchor_min_level..=md_options.anchor_max_level,
+            2..=3
+        );
+        assert_eq!(md_options.permalink, "name");
+        assert!(md_options.pretty_urls);
+        assert!(md_options.trailing_slash);
+
+        let md = render_markdown_str("# a\n\n```rust\nfn main() {}\n```\n", &md_options).unwrap();
+        assert!(
+            md.html.contains("<span class=\"source rust\">"),
+            "{}",
+            md.html
+        );
+    }
+
+    #[test]
+    fn unknown_extensions_dropped() {
+        let config = config::Config {
+            markdown: BTreeMap::from([
+                ("tasklist".to_string(), false),
+                ("no_such_extension".to_string(), true),
+            ]),
+            ..Default::default()
+        };
+        let md_options = MarkdownOptions::from_config(&config, Highlighter::default());
+        assert_eq!(
+            md_options.extensions,
+            BTreeMap::from([("tasklist".to_string(), false)])
+        );
+
+        let md = render_markdown_str("# a\n\n- [x] done\n", &md_options).unwrap();
+        assert!(!md.html.contains("checkbox"), "{}", md.html);
+    }
+
+    #[test]
+    fn highlight_with_theme() {
+        let md_options = MarkdownOptions {
+            highlighter: Highlighter::new(Some("InspiredGitHub"), None).unwrap(),
+            ..Default::default()
+        };
+        let md = render_markdown_str("# a\n\n```rust\nfn main() {}\n```\n", &md_options).unwrap();
+        assert!(md.html.contains("style=\"color:"), "{}", md.html);
+        assert!(!md.html.contains("class=\"source rust\""));
+    }
+}
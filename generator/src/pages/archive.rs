@@ -0,0 +1,40 @@
+use chrono::Datelike as _;
+use hypertext::prelude::*;
+
+use crate::pages;
+
+pub struct Archive<'a> {
+    /// Sorted newest first
+    pub blog_entries: &'a [pages::BlogEntry<'a>],
+}
+
+impl Renderable for Archive<'_> {
+    fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
+        let years: Vec<(i32, Vec<&pages::BlogEntry>)> = self
+            .blog_entries
+            .chunk_by(|a, b| a.publish_time.year() == b.publish_time.year())
+            .map(|entries| (entries[0].publish_time.year(), entries.iter().collect()))
+            .collect();
+
+        rsx! {
+            <div class="blog-archive">
+                @for (year, entries) in &years {
+                    <h2>(year.to_string())</h2>
+
+                    <ul>
+                        @for entry in entries {
+                            <li>
+                                <span class="blog-date">
+                                    (entry.publish_time.format(entry.date_format).to_string())
+                                </span>
+                                <span>" "</span>
+                                <a href=(entry.url())>(entry.title)</a>
+                            </li>
+                        }
+                    </ul>
+                }
+            </div>
+        }
+        .render_to(buffer);
+    }
+}
@@ -1,4 +1,21 @@
-use hypertext::prelude::*;
+use std::collections::BTreeMap;
+
+use hypertext::{Raw, prelude::*};
+
+use crate::{
+    config::{FeedFormat, HeadHints, Icon, ThemeColor},
+    static_dir::Assets,
+};
+
+const KATEX_CSS: &str = "https://cdn.jsdelivr.net/npm/katex@0.16.22/dist/katex.min.css";
+const KATEX_JS: &str = "https://cdn.jsdelivr.net/npm/katex@0.16.22/dist/katex.min.js";
+/// Imported by `/static/js/mermaid.js`
+const MERMAID_ESM: &str = "https://cdn.jsdelivr.net/npm/mermaid@11.4.1/dist/mermaid.esm.min.mjs";
+const MAIN_STYLESHEET: &str = "/static/styles.css";
+const PRINT_STYLESHEET: &str = "/static/print.css";
+/// Sets the theme chosen by the toggle before paint, its hash is allowed by
+/// `my_site_web::DEFAULT_CSP`, update both together (checked by a test of the generator)
+const THEME_SCRIPT: &str = r#"try{const t=localStorage.getItem("theme");if(t==="light"||t==="dark")document.documentElement.dataset.theme=t}catch{}"#;
 
 #[derive(Clone)]
 pub struct Base<'a, T: Renderable> {
@@ -8,16 +25,42 @@ pub struct Base<'a, T: Renderable> {
 
 #[derive(Clone)]
 pub struct Head<'a> {
+    /// `<html lang>`
+    pub lang: &'a str,
     pub title: &'a str,
     pub description: Option<&'a str>,
     pub author: &'a str,
+    pub viewport: &'a str,
+    pub theme_color: Option<&'a ThemeColor>,
+    pub canonical: &'a str,
+    /// Language -> url of the translations of the page
+    pub translations: Option<&'a BTreeMap<String, String>>,
+    pub favicon: &'a [Icon],
+    pub feeds: &'a [FeedFormat],
+    /// RSS of the tag page
+    pub tag_feed: Option<&'a str>,
+    pub syntax_css: bool,
+    pub has_math: bool,
+    pub has_mermaid: bool,
+    /// `<html data-theme>` and the scripts of the theme toggle
+    pub theme_toggle: bool,
+    /// Stylesheet for `media="print"`
+    pub print_stylesheet: bool,
+    /// Extra stylesheets of the page type
+    pub stylesheets: &'a [&'a str],
+    /// Extra stylesheets and scripts of the page, from the frontmatter
+    pub page_styles: &'a [String],
+    pub page_scripts: &'a [String],
+    /// Fingerprinted urls of the static assets
+    pub assets: &'a Assets,
+    pub hints: &'a HeadHints,
 }
 
 impl<T: Renderable> Renderable for Base<'_, T> {
     fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
         rsx! {
             <!DOCTYPE html>
-            <html>
+            <html lang=(self.head.lang) data-theme=@if self.head.theme_toggle { "auto" }>
                 (self.head)
                 (self.body)
             </html>
@@ -26,12 +69,76 @@ impl<T: Renderable> Renderable for Base<'_, T> {
     }
 }
 
+impl Head<'_> {
+    /// Logical urls of the stylesheets and scripts loaded by the page
+    fn resources(&self) -> Vec<&str> {
+        let mut ret = vec![MAIN_STYLESHEET, "/static/js/copy.js"];
+        if self.print_stylesheet {
+            ret.push(PRINT_STYLESHEET);
+        }
+        if self.theme_toggle {
+            ret.push("/static/js/theme.js");
+        }
+        ret.extend(self.stylesheets);
+        if self.syntax_css {
+            ret.push("/static/syntax.css");
+        }
+        if self.has_math {
+            ret.extend([KATEX_CSS, KATEX_JS, "/static/js/math.js"]);
+        }
+        if self.has_mermaid {
+            ret.extend(["/static/js/mermaid.js", MERMAID_ESM]);
+        }
+        ret.extend(self.page_styles.iter().map(String::as_str));
+        ret.extend(self.page_scripts.iter().map(String::as_str));
+        ret
+    }
+}
+
 impl Renderable for Head<'_> {
     fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
+        // only hint what the page uses, unused preloads are warned by the browsers
+        let resources = self.resources();
+        let origins: Vec<_> = resources.iter().filter_map(|x| url_origin(x)).collect();
+        let preloads: Vec<_> = self
+            .hints
+            .preload
+            .iter()
+            .filter(|x| {
+                !matches!(preload_as(x), Some("style" | "script"))
+                    || resources.contains(&x.as_str())
+            })
+            .collect();
+        let preconnects: Vec<_> = self
+            .hints
+            .preconnect
+            .iter()
+            .filter(|x| origins.contains(&x.origin.as_str()))
+            .collect();
+        let dns_prefetches: Vec<_> = self
+            .hints
+            .dns_prefetch
+            .iter()
+            .filter(|x| origins.contains(&x.as_str()))
+            .collect();
+
         rsx! {
             <head>
                 <meta charset="UTF-8">
-                <meta name="viewport" content="width=device-width, initial-scale=1">
+                <meta name="viewport" content=(self.viewport)>
+                @if self.theme_toggle {
+                    <script>(Raw::dangerously_create(THEME_SCRIPT))</script>
+                }
+                @match self.theme_color {
+                    Some(ThemeColor::Single(color)) => {
+                        <meta name="theme-color" content=(color)>
+                    }
+                    Some(ThemeColor::Scheme { light, dark }) => {
+                        <meta name="theme-color" content=(light) media="(prefers-color-scheme: light)">
+                        <meta name="theme-color" content=(dark) media="(prefers-color-scheme: dark)">
+                    }
+                    None => {}
+                }
 
                 <title>(self.title)</title>
 
@@ -39,11 +146,124 @@ impl Renderable for Head<'_> {
                     <meta name="description" content=(description)>
                 }
                 <meta name="author" content=(self.author)>
+                <link rel="canonical" href=(self.canonical)>
+                @if let Some(translations) = self.translations.filter(|x| !x.is_empty()) {
+                    <link rel="alternate" hreflang=(self.lang) href=(self.canonical)>
+                    @for (lang, url) in translations {
+                        <link rel="alternate" hreflang=(lang) href=(url)>
+                    }
+                }
 
-                <link rel="icon" href="/favicon.svg" type="image/svg+xml" >
-                <link rel="stylesheet" href="/static/styles.css">
+                @if self.hints.preload_stylesheet {
+                    <link rel="preload" href=(self.assets.url(MAIN_STYLESHEET)) as="style">
+                }
+                @for url in &preloads {
+                    @if let Some(as_type) = preload_as(url) {
+                        @if as_type == "font" {
+                            <link rel="preload" href=(self.assets.url(url)) as=(as_type) crossorigin>
+                        } @else {
+                            <link rel="preload" href=(self.assets.url(url)) as=(as_type)>
+                        }
+                    }
+                }
+                @for hint in &preconnects {
+                    @if hint.crossorigin {
+                        <link rel="preconnect" href=(hint.origin) crossorigin>
+                    } @else {
+                        <link rel="preconnect" href=(hint.origin)>
+                    }
+                }
+                @for origin in &dns_prefetches {
+                    <link rel="dns-prefetch" href=(origin)>
+                }
+
+                @for icon in self.favicon {
+                    (icon)
+                }
+                <link rel="stylesheet" href=(self.assets.url(MAIN_STYLESHEET))>
+                @if self.print_stylesheet {
+                    <link rel="stylesheet" href=(self.assets.url(PRINT_STYLESHEET)) media="print">
+                }
+                @for stylesheet in self.stylesheets {
+                    <link rel="stylesheet" href=(self.assets.url(stylesheet))>
+                }
+                @if self.syntax_css {
+                    <link rel="stylesheet" href=(self.assets.url("/static/syntax.css"))>
+                }
+                @for style in self.page_styles {
+                    <link rel="stylesheet" href=(self.assets.url(style))>
+                }
+                @for feed in self.feeds {
+                    <link rel="alternate" type=(feed.mime_type()) href=(format!("/{}", feed.path()))>
+                }
+                @if let Some(tag_feed) = self.tag_feed {
+                    <link rel="alternate" type=(FeedFormat::Rss.mime_type()) href=(tag_feed)>
+                }
+                <script defer src=(self.assets.url("/static/js/copy.js"))></script>
+                @if self.theme_toggle {
+                    <script defer src=(self.assets.url("/static/js/theme.js"))></script>
+                }
+
+                @if self.has_math {
+                    <link rel="stylesheet" href=(KATEX_CSS)>
+                    <script defer src=(KATEX_JS)></script>
+                    <script defer src=(self.assets.url("/static/js/math.js"))></script>
+                }
+                @if self.has_mermaid {
+                    <script type="module" src=(self.assets.url("/static/js/mermaid.js"))></script>
+                }
+                @for script in self.page_scripts {
+                    <script defer src=(self.assets.url(script))></script>
+                }
             </head>
         }
         .render_to(buffer);
     }
 }
+
+/// `https://example.com/a/b` -> `https://example.com`, `None` if not an absolute url
+pub fn url_origin(url: &str) -> Option<&str> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .filter(|x| !x.is_empty())?;
+
+    let host_len = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    Some(&url[..url.len() - rest.len() + host_len])
+}
+
+/// `as` of `<link rel="preload">` by the extension
+fn preload_as(url: &str) -> Option<&'static str> {
+    let ext = url.rsplit_once('.')?.1;
+    match ext {
+        "css" => Some("style"),
+        "js" | "mjs" => Some("script"),
+        "woff2" | "woff" | "ttf" | "otf" => Some("font"),
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "avif" | "svg" => Some("image"),
+        _ => None,
+    }
+}
+
+impl Renderable for Icon {
+    fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
+        let (rel, href) = (&self.rel, &self.href);
+        match (&self.sizes, &self.mime_type) {
+            (Some(sizes), Some(mime_type)) => rsx! {
+                <link rel=(rel) href=(href) sizes=(sizes) type=(mime_type)>
+            }
+            .render_to(buffer),
+            (Some(sizes), None) => rsx! {
+                <link rel=(rel) href=(href) sizes=(sizes)>
+            }
+            .render_to(buffer),
+            (None, Some(mime_type)) => rsx! {
+                <link rel=(rel) href=(href) type=(mime_type)>
+            }
+            .render_to(buffer),
+            (None, None) => rsx! {
+                <link rel=(rel) href=(href)>
+            }
+            .render_to(buffer),
+        }
+    }
+}
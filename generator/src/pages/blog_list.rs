@@ -6,6 +6,7 @@ use crate::{config, pages};
 
 pub struct BlogHome<'a> {
     pub blog_entries: &'a [BlogEntry<'a>],
+    pub url_style: config::UrlStyle,
 }
 
 pub struct BlogTagHome<'a> {
@@ -19,21 +20,41 @@ pub struct BlogEntry<'a> {
     pub title: &'a str,
     pub rel_path: &'a Path,
     pub tags: &'a [String],
+    pub excerpt_html: Option<&'a str>,
+    pub url_style: config::UrlStyle,
+    pub date_format: &'a str,
+}
+
+impl BlogEntry<'_> {
+    /// `/blog/yyyy-mm-dd-blog-slug`, depends on the `permalink` config
+    pub fn url(&self) -> String {
+        config::page_link(self.rel_path, self.url_style)
+    }
 }
 
 impl Renderable for BlogHome<'_> {
     fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
         rsx! {
             <div class="blog-home">
-                <div class="blog-list">
-                    <ul>
-                        @for entry in self.blog_entries {
-                            <li>
-                                (entry)
-                            </li>
-                        }
-                    </ul>
-                </div>
+                <p class="blog-home-links">
+                    <a href=(config::page_link("blog/archive", self.url_style))>"archive"</a>
+                    <span>" "</span>
+                    <a href=(config::page_link("blog/tags", self.url_style))>"tags"</a>
+                </p>
+
+                @if self.blog_entries.is_empty() {
+                    <p class="blog-empty">"No posts yet."</p>
+                } @else {
+                    <div class="blog-list">
+                        <ul>
+                            @for entry in self.blog_entries {
+                                <li>
+                                    (entry)
+                                </li>
+                            }
+                        </ul>
+                    </div>
+                }
             </div>
         }
         .render_to(buffer);
@@ -63,13 +84,12 @@ impl Renderable for BlogTagHome<'_> {
 
 impl Renderable for BlogEntry<'_> {
     fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
-        let url = Path::new("/").join(self.rel_path);
-        let url = url.to_str().unwrap_or("/");
+        let url = self.url();
 
         rsx! {
             <div class="blog-entry">
                 <div class="blog-date">
-                    <p>(self.publish_time.to_string())</p>
+                    <p>(self.publish_time.format(self.date_format).to_string())</p>
                 </div>
 
                 <div class="blog-link">
@@ -83,12 +103,18 @@ impl Renderable for BlogEntry<'_> {
                         <p>
                             @for tag in self.tags {
                                 <span>" "</span>
-                                <a href=(config::tag_to_link(tag))>
+                                <a href=(config::tag_to_link(tag, self.url_style))>
                                     "#"(tag)
                                 </a>
                             }
                         </p>
                     </div>
+
+                    @if let Some(excerpt_html) = self.excerpt_html {
+                        <div class="blog-excerpt">
+                            (Raw::dangerously_create(excerpt_html))
+                        </div>
+                    }
                 </div>
             </div>
         }
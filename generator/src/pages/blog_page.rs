@@ -3,10 +3,21 @@ use hypertext::{Raw, prelude::*};
 use crate::{config, generator, pages};
 
 pub struct BlogPage<'a> {
+    pub author: &'a str,
     pub publish_time: chrono::NaiveDate,
     pub last_update_time: chrono::NaiveDate,
     pub last_commit: Option<&'a generator::BlogCommit>,
+    /// Distinct authors of the commits, empty if not shown
+    pub contributors: Vec<&'a str>,
+    pub commits: pages::Commits<'a>,
     pub markdown: &'a crate::markdown::Markdown,
+    pub related: &'a [pages::BlogEntry<'a>],
+    pub newer: Option<pages::BlogEntry<'a>>,
+    pub older: Option<pages::BlogEntry<'a>>,
+    pub series: Option<pages::Series<'a>>,
+    pub comments: Option<pages::Comments<'a>>,
+    pub url_style: config::UrlStyle,
+    pub date_format: &'a str,
 }
 
 impl Renderable for BlogPage<'_> {
@@ -18,8 +29,12 @@ impl Renderable for BlogPage<'_> {
         rsx! {
             <div class="blog">
                 <div class="blog-info">
-                    <p> "publish: " (self.publish_time.to_string()) </p>
-                    <p> "update: " (self.last_update_time.to_string()) </p>
+                    <p> "author: " (self.author) </p>
+                    @if !self.contributors.is_empty() {
+                        <p class="blog-contributors"> "contributors: " (self.contributors.join(", ")) </p>
+                    }
+                    <p> "publish: " (self.publish_time.format(self.date_format).to_string()) </p>
+                    <p> "update: " (self.last_update_time.format(self.date_format).to_string()) </p>
                     @if let Some(commit) = self.last_commit {
                         <p>
                             <span>"commit: "</span>
@@ -28,18 +43,67 @@ impl Renderable for BlogPage<'_> {
                             </a>
                         </p>
                     }
+                    (self.commits)
+                    @if !self.markdown.meta.translations.is_empty() {
+                        <p class="blog-translations">
+                            <span>"translations:"</span>
+                            @for (lang, url) in &self.markdown.meta.translations {
+                                <span>" "</span>
+                                <a href=(url) hreflang=(lang) lang=(lang)>(lang)</a>
+                            }
+                        </p>
+                    }
                     <p>
                         <span>"tags:"</span>
                         @for tag in &self.markdown.meta.tags {
                             <span>" "</span>
-                            <a href=(config::tag_to_link(tag))>
+                            <a href=(config::tag_to_link(tag, self.url_style))>
                                 "#"(tag)
                             </a>
                         }
                     </p>
                 </div>
 
+                @if let Some(series) = &self.series {
+                    (series)
+                }
+
                 (article)
+
+                @if let Some(comments) = &self.comments {
+                    (comments)
+                }
+
+                @if self.older.is_some() || self.newer.is_some() {
+                    <nav class="blog-nav">
+                        <div class="blog-nav-older">
+                            @if let Some(older) = &self.older {
+                                <a href=(older.url())>"← " (older.title)</a>
+                            }
+                        </div>
+                        <div class="blog-nav-newer">
+                            @if let Some(newer) = &self.newer {
+                                <a href=(newer.url())>(newer.title) " →"</a>
+                            }
+                        </div>
+                    </nav>
+                }
+
+                @if !self.related.is_empty() {
+                    <div class="blog-related">
+                        <h2>"Related posts"</h2>
+
+                        <div class="blog-list">
+                            <ul>
+                                @for entry in self.related {
+                                    <li>
+                                        (entry)
+                                    </li>
+                                }
+                            </ul>
+                        </div>
+                    </div>
+                }
             </div>
         }
         .render_to(buffer);
@@ -0,0 +1,35 @@
+use hypertext::prelude::*;
+
+use crate::config;
+
+pub struct Blogroll<'a> {
+    pub feeds: &'a [config::BlogrollFeed],
+    /// Url of the OPML export
+    pub opml_url: &'a str,
+}
+
+impl Renderable for Blogroll<'_> {
+    fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
+        rsx! {
+            <div class="blogroll">
+                <h1>"Blogroll"</h1>
+                <ul>
+                    @for feed in self.feeds {
+                        <li>
+                            @if let Some(html_url) = &feed.html_url {
+                                <a href=(html_url)>(feed.title)</a>
+                            } @else {
+                                (feed.title)
+                            }
+                            " ("<a href=(feed.xml_url)>"feed"</a>")"
+                        </li>
+                    }
+                </ul>
+                <p>
+                    <a href=(self.opml_url)>"OPML"</a>
+                </p>
+            </div>
+        }
+        .render_to(buffer);
+    }
+}
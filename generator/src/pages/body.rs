@@ -1,4 +1,4 @@
-use hypertext::prelude::*;
+use hypertext::{Raw, prelude::*};
 
 use crate::config;
 
@@ -6,6 +6,7 @@ use crate::config;
 pub struct Body<'a, T: Renderable> {
     pub header: Header<'a>,
     pub footer: Footer<'a>,
+    pub layout: config::Layout,
     pub main: T,
 }
 
@@ -14,23 +15,34 @@ pub struct Header<'a> {
     pub home_name: &'a String,
     pub links: &'a [config::Link],
     pub active_url: Option<&'a str>,
+    pub theme_toggle: bool,
+    /// `partials/header.html` replacing the built-in markup,
+    /// with `{{home_name}}`, `{{links}}` and `{{theme_toggle}}` substituted
+    pub partial: Option<&'a str>,
 }
 
 #[derive(Clone)]
 pub struct Footer<'a> {
     pub links: &'a [config::Link],
     pub cc_text: &'a str,
+    /// `partials/footer.html` replacing the built-in markup,
+    /// with `{{links}}` and `{{cc}}` substituted
+    pub partial: Option<&'a str>,
 }
 
 impl<T: Renderable> Renderable for Body<'_, T> {
     fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
         rsx! {
-            <body>
-                (self.header)
+            <body class=(self.layout.class())>
+                @if self.layout != config::Layout::Minimal {
+                    (self.header)
+                }
                 <main>
                     (self.main)
                 </main>
-                (self.footer)
+                @if self.layout != config::Layout::Minimal {
+                    (self.footer)
+                }
             </body>
         }
         .render_to(buffer);
@@ -43,10 +55,36 @@ impl Renderable for Header<'_> {
             let link_url = link_url.trim_matches('/');
             self.active_url.is_some_and(|x| {
                 let strip = x.strip_prefix(link_url);
-                strip.is_some_and(|s| s.chars().next().is_none_or(|c| c == '/'))
+                // `blog/index.html` or `blog.html` without pretty urls
+                strip.is_some_and(|s| s.chars().next().is_none_or(|c| c == '/' || c == '.'))
             })
         };
 
+        let links = rsx! {
+            @for link in self.links {
+                <a href=(link.url) class=@if is_active(&link.url) { "active" }>
+                    (link.title)
+                </a>
+            }
+        };
+        let theme_toggle = rsx! {
+            @if self.theme_toggle {
+                <button id="theme-toggle" type="button" aria-label="Toggle theme">"◐"</button>
+            }
+        };
+
+        if let Some(partial) = self.partial {
+            let html = partial
+                .replace(
+                    "{{home_name}}",
+                    &rsx! { (self.home_name) }.render().into_inner(),
+                )
+                .replace("{{links}}", &links.render().into_inner())
+                .replace("{{theme_toggle}}", &theme_toggle.render().into_inner());
+            Raw::dangerously_create(&html).render_to(buffer);
+            return;
+        }
+
         rsx! {
             <header>
                 <div class="header-home">
@@ -54,11 +92,8 @@ impl Renderable for Header<'_> {
                 </div>
 
                 <div class="header-links">
-                    @for link in self.links {
-                        <a href=(link.url) class=@if is_active(&link.url) { "active" }>
-                            (link.title)
-                        </a>
-                    }
+                    (links)
+                    (theme_toggle)
                 </div>
             </header>
         }
@@ -68,16 +103,28 @@ impl Renderable for Header<'_> {
 
 impl Renderable for Footer<'_> {
     fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
+        let links = rsx! {
+            <ul>
+                @for link in self.links {
+                    <li>
+                        <a href=(link.url)>(link.title)</a>
+                    </li>
+                }
+            </ul>
+        };
+
+        if let Some(partial) = self.partial {
+            let html = partial
+                .replace("{{links}}", &links.render().into_inner())
+                .replace("{{cc}}", &rsx! { (self.cc_text) }.render().into_inner());
+            Raw::dangerously_create(&html).render_to(buffer);
+            return;
+        }
+
         rsx! {
             <footer>
                 <div class="footer-links">
-                    <ul>
-                        @for link in self.links {
-                            <li>
-                                <a href=(link.url)>(link.title)</a>
-                            </li>
-                        }
-                    </ul>
+                    (links)
                 </div>
 
                 <div class="footer-cc">
@@ -0,0 +1,39 @@
+use hypertext::{Raw, prelude::*};
+
+use crate::config;
+
+/// Container and script of the comment widget
+pub struct Comments<'a> {
+    pub config: &'a config::Comments,
+    /// Stable id of the post, see `BlogEntry::comment_id`
+    pub comment_id: &'a str,
+}
+
+impl Renderable for Comments<'_> {
+    fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
+        // the attribute names are configured, so the script is written by hand
+        let mut script = format!("<script src=\"{}\"", escape(&self.config.script));
+        for (name, value) in &self.config.attributes {
+            script.push_str(&format!(" {}=\"{}\"", escape(name), escape(value)));
+        }
+        script.push_str(&format!(
+            " {}=\"{}\" async></script>",
+            escape(&self.config.id_attribute),
+            escape(self.comment_id)
+        ));
+
+        rsx! {
+            <section class="blog-comments">
+                (Raw::dangerously_create(&script))
+            </section>
+        }
+        .render_to(buffer);
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
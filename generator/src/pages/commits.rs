@@ -0,0 +1,50 @@
+use hypertext::prelude::*;
+
+use crate::generator;
+
+/// Commit history of a blog post
+pub struct Commits<'a> {
+    /// All commits, newest first
+    pub commits: &'a [generator::BlogCommit],
+    pub max_commits: usize,
+    /// History of the source file on the forge
+    pub history_url: Option<String>,
+    pub date_format: &'a str,
+}
+
+impl Renderable for Commits<'_> {
+    fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
+        if self.commits.len() <= 1 || self.max_commits == 0 {
+            return;
+        }
+
+        let shown = &self.commits[..self.commits.len().min(self.max_commits)];
+        let more = self.commits.len() - shown.len();
+
+        rsx! {
+            <details class="blog-commits">
+                <summary>"history"</summary>
+                <ul>
+                    @for commit in shown {
+                        <li>
+                            <span>(commit.time.format(self.date_format).to_string()) " "</span>
+                            <a href=(format!("{}/{}", commit.base_url, commit.hash))>
+                                (commit.hash[..7]) " - " (commit.summary)
+                            </a>
+                        </li>
+                    }
+                    @if more > 0 {
+                        <li>
+                            @if let Some(history_url) = &self.history_url {
+                                <a href=(history_url)>"… and " (more.to_string()) " more"</a>
+                            } @else {
+                                "… and " (more.to_string()) " more"
+                            }
+                        </li>
+                    }
+                </ul>
+            </details>
+        }
+        .render_to(buffer);
+    }
+}
@@ -1,13 +1,29 @@
 #![allow(unused_imports)]
 
+mod archive;
 mod article;
 mod base;
 mod blog_list;
 mod blog_page;
+mod blogroll;
 mod body;
+mod comments;
+mod commits;
+mod redirect;
+mod series;
+mod stats;
+mod tags_index;
 
+pub use archive::Archive;
 pub use article::Article;
-pub use base::{Base, Head};
+pub use base::{Base, Head, url_origin};
 pub use blog_list::{BlogEntry, BlogHome, BlogTagHome};
 pub use blog_page::BlogPage;
+pub use blogroll::Blogroll;
 pub use body::{Body, Footer, Header};
+pub use comments::Comments;
+pub use commits::Commits;
+pub use redirect::Redirect;
+pub use series::{Series, SeriesHome};
+pub use stats::Stats;
+pub use tags_index::TagsIndex;
@@ -0,0 +1,28 @@
+use hypertext::prelude::*;
+
+/// Stub page redirecting an alias to the real page
+pub struct Redirect<'a> {
+    /// Absolute url of the real page
+    pub url: &'a str,
+}
+
+impl Renderable for Redirect<'_> {
+    fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
+        rsx! {
+            <!DOCTYPE html>
+            <html>
+                <head>
+                    <meta charset="UTF-8">
+                    <title>"Redirecting…"</title>
+                    <link rel="canonical" href=(self.url)>
+                    <meta name="robots" content="noindex">
+                    <meta http-equiv="refresh" content=(format!("0; url={}", self.url))>
+                </head>
+                <body>
+                    <p>"Moved to " <a href=(self.url)>(self.url)</a></p>
+                </body>
+            </html>
+        }
+        .render_to(buffer);
+    }
+}
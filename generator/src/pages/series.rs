@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use hypertext::prelude::*;
+
+use crate::{config, pages};
+
+/// The box on a blog page listing all parts of its series
+#[derive(Clone, Copy)]
+pub struct Series<'a> {
+    pub name: &'a str,
+    /// Sorted by publish time
+    pub parts: &'a [pages::BlogEntry<'a>],
+    /// `rel_path` of the current blog
+    pub current: &'a Path,
+    pub url_style: config::UrlStyle,
+}
+
+pub struct SeriesHome<'a> {
+    pub name: &'a str,
+    /// Sorted by publish time
+    pub blog_entries: &'a [pages::BlogEntry<'a>],
+}
+
+impl Renderable for Series<'_> {
+    fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
+        rsx! {
+            <div class="blog-series">
+                <p>
+                    <span>"series: "</span>
+                    <a href=(config::series_to_link(self.name, self.url_style))>(self.name)</a>
+                </p>
+
+                <ol>
+                    @for part in self.parts {
+                        @if part.rel_path == self.current {
+                            <li class="active">(part.title)</li>
+                        } @else {
+                            <li>
+                                <a href=(part.url())>(part.title)</a>
+                            </li>
+                        }
+                    }
+                </ol>
+            </div>
+        }
+        .render_to(buffer);
+    }
+}
+
+impl Renderable for SeriesHome<'_> {
+    fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
+        rsx! {
+            <div class="blog-series-home">
+                <h3>"series: "(self.name)</h3>
+
+                <div class="blog-list">
+                    <ul>
+                        @for entry in self.blog_entries {
+                            <li>
+                                (entry)
+                            </li>
+                        }
+                    </ul>
+                </div>
+            </div>
+        }
+        .render_to(buffer);
+    }
+}
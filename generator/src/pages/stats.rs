@@ -0,0 +1,52 @@
+use hypertext::prelude::*;
+
+use crate::config;
+
+pub struct Stats<'a> {
+    pub total_posts: usize,
+    pub total_words: usize,
+    /// `(tag, post count)`, sorted by post count
+    pub tags: &'a [(&'a str, usize)],
+    /// `(year, post count)`, newest first
+    pub years: &'a [(i32, usize)],
+    pub url_style: config::UrlStyle,
+}
+
+impl Renderable for Stats<'_> {
+    fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
+        let average_words = self.total_words.checked_div(self.total_posts).unwrap_or(0);
+
+        rsx! {
+            <div class="blog-stats">
+                <ul>
+                    <li>"posts: " (self.total_posts.to_string())</li>
+                    <li>"words: " (self.total_words.to_string())</li>
+                    <li>"words per post: " (average_words.to_string())</li>
+                </ul>
+
+                <h2>"Posts per year"</h2>
+                <table>
+                    @for (year, count) in self.years {
+                        <tr>
+                            <td>(year.to_string())</td>
+                            <td>(count.to_string())</td>
+                        </tr>
+                    }
+                </table>
+
+                <h2>"Posts per tag"</h2>
+                <table>
+                    @for (tag, count) in self.tags {
+                        <tr>
+                            <td>
+                                <a href=(config::tag_to_link(tag, self.url_style))>"#"(tag)</a>
+                            </td>
+                            <td>(count.to_string())</td>
+                        </tr>
+                    }
+                </table>
+            </div>
+        }
+        .render_to(buffer);
+    }
+}
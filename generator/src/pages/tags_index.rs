@@ -0,0 +1,27 @@
+use hypertext::prelude::*;
+
+use crate::config;
+
+pub struct TagsIndex<'a> {
+    /// `(tag, post count)`, sorted by post count
+    pub tags: &'a [(&'a str, usize)],
+    pub url_style: config::UrlStyle,
+}
+
+impl Renderable for TagsIndex<'_> {
+    fn render_to(&self, buffer: &mut hypertext::Buffer<hypertext::context::Node>) {
+        rsx! {
+            <div class="blog-tags-index">
+                <ul>
+                    @for (tag, count) in self.tags {
+                        <li>
+                            <a href=(config::tag_to_link(tag, self.url_style))>"#"(tag)</a>
+                            <span class="blog-tag-count">" (" (count.to_string()) ")"</span>
+                        </li>
+                    }
+                </ul>
+            </div>
+        }
+        .render_to(buffer);
+    }
+}
@@ -1,34 +1,127 @@
-use std::{fs, path::Path};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
 
 use include_dir::{Dir, include_dir};
 
+use crate::config;
+
 static STATIC_DIR: Dir = include_dir!("$OUT_DIR/static");
 
-pub fn copy_static_dir_to(out_dir: impl AsRef<Path>) -> std::io::Result<()> {
+/// Extensions of the static files that get a content hash in the filename
+const FINGERPRINT_EXTENSIONS: &[&str] = &["css", "js"];
+
+/// Map from the logical url of a static asset (e.g. `/static/styles.css`)
+/// to the fingerprinted one (e.g. `/static/styles.0123456789abcdef.css`)
+#[derive(Debug, Clone, Default)]
+pub struct Assets {
+    urls: HashMap<String, String>,
+}
+
+impl Assets {
+    /// Fingerprinted url of the asset, or the url itself if it's not fingerprinted
+    pub fn url<'a>(&'a self, url: &'a str) -> &'a str {
+        self.urls.get(url).map_or(url, String::as_str)
+    }
+
+    /// Write `contents` to `out_dir/rel_path` with a content hash in the filename
+    /// if the extension should be fingerprinted, and record the mapping.
+    /// Returns the written path.
+    pub fn write(
+        &mut self,
+        out_dir: impl AsRef<Path>,
+        rel_path: impl AsRef<Path>,
+        contents: &[u8],
+    ) -> std::io::Result<PathBuf> {
+        let rel_path = rel_path.as_ref();
+
+        let should_fingerprint = rel_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| FINGERPRINT_EXTENSIONS.contains(&ext));
+
+        let out_rel_path = if should_fingerprint {
+            let hashed = fingerprint(rel_path, contents);
+            self.urls.insert(static_url(rel_path), static_url(&hashed));
+            hashed
+        } else {
+            rel_path.to_path_buf()
+        };
+
+        // only create dir when needed
+        let path = out_dir.as_ref().join(out_rel_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+}
+
+pub fn copy_static_dir_to(out_dir: impl AsRef<Path>) -> std::io::Result<Assets> {
     let out_dir = out_dir.as_ref();
     fs::create_dir_all(out_dir)?;
-    copy_dir(&STATIC_DIR, out_dir)
+
+    let mut assets = Assets::default();
+    copy_dir(&STATIC_DIR, out_dir, &mut assets)?;
+    Ok(assets)
 }
 
-fn copy_dir(dir: &Dir, out_dir: impl AsRef<Path>) -> std::io::Result<()> {
+fn copy_dir(dir: &Dir, out_dir: impl AsRef<Path>, assets: &mut Assets) -> std::io::Result<()> {
     let out_dir = out_dir.as_ref();
 
     for entry in dir.entries() {
         match entry {
             include_dir::DirEntry::Dir(subdir) => {
-                copy_dir(subdir, out_dir)?;
+                copy_dir(subdir, out_dir, assets)?;
             }
             include_dir::DirEntry::File(file) => {
-                // only create dir when needed
-                let path = out_dir.join(file.path());
-                if let Some(parent) = path.parent() {
-                    fs::create_dir_all(parent)?;
-                }
+                let path = assets.write(out_dir, file.path(), file.contents())?;
 
-                fs::write(path, file.contents())?;
+                // keep the mtime of the source file for mtime based caching,
+                // the build script copies it to the embedded dir
+                if let Some(metadata) = file.metadata() {
+                    fs::File::options()
+                        .write(true)
+                        .open(path)?
+                        .set_modified(metadata.modified())?;
+                }
             }
         }
     }
 
     Ok(())
 }
+
+/// `css/styles.css` -> `css/styles.<hash>.css`
+fn fingerprint(rel_path: &Path, contents: &[u8]) -> PathBuf {
+    let mut file_name = OsString::new();
+    if let Some(stem) = rel_path.file_stem() {
+        file_name.push(stem);
+    }
+    file_name.push(format!(".{:016x}", fnv1a(contents)));
+    if let Some(ext) = rel_path.extension() {
+        file_name.push(".");
+        file_name.push(ext);
+    }
+
+    rel_path.with_file_name(file_name)
+}
+
+fn static_url(rel_path: &Path) -> String {
+    format!("/{}/{}", config::STATIC_DIR, rel_path.display())
+}
+
+/// FNV-1a, stable across builds and Rust versions unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
@@ -0,0 +1,14 @@
+// copy buttons on code blocks
+document.addEventListener("DOMContentLoaded", () => {
+  for (const button of document.querySelectorAll(".code-block > button.copy")) {
+    button.addEventListener("click", async () => {
+      const code = button.parentElement.querySelector("pre code") ??
+        button.parentElement.querySelector("pre");
+
+      await navigator.clipboard.writeText(code.innerText);
+
+      button.textContent = "copied";
+      setTimeout(() => (button.textContent = "copy"), 1500);
+    });
+  }
+});
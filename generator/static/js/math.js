@@ -0,0 +1,9 @@
+// render math emitted by comrak (`$...$`, `$$...$$`) with KaTeX
+document.addEventListener("DOMContentLoaded", () => {
+  for (const el of document.querySelectorAll("[data-math-style]")) {
+    katex.render(el.textContent, el, {
+      displayMode: el.dataset.mathStyle === "display",
+      throwOnError: false,
+    });
+  }
+});
@@ -0,0 +1,4 @@
+// render ```mermaid code blocks, emitted as `<pre class="mermaid">`
+import mermaid from "https://cdn.jsdelivr.net/npm/mermaid@11.4.1/dist/mermaid.esm.min.mjs";
+
+mermaid.initialize({ startOnLoad: true });
@@ -0,0 +1,18 @@
+// toggle between the light and dark themes, remembered in localStorage
+document.addEventListener("DOMContentLoaded", () => {
+  const button = document.getElementById("theme-toggle");
+  if (!button) return;
+
+  button.addEventListener("click", () => {
+    const root = document.documentElement;
+    const current = root.dataset.theme === "auto"
+      ? (matchMedia("(prefers-color-scheme: light)").matches ? "light" : "dark")
+      : root.dataset.theme;
+    const next = current === "light" ? "dark" : "light";
+
+    root.dataset.theme = next;
+    try {
+      localStorage.setItem("theme", next);
+    } catch {}
+  });
+});
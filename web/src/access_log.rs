@@ -0,0 +1,89 @@
+use std::{net::SocketAddr, str::FromStr, time::Instant};
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+
+/// Format of the access log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Spans and events of `TraceLayer`.
+    #[default]
+    Pretty,
+    /// One Apache combined log line per request, with the duration in milliseconds appended.
+    Combined,
+}
+
+#[derive(Debug)]
+pub struct InvalidLogFormat(String);
+
+impl std::fmt::Display for InvalidLogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown log format `{}`, expected `pretty` or `combined`",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidLogFormat {}
+
+impl FromStr for LogFormat {
+    type Err = InvalidLogFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(Self::Pretty),
+            "combined" => Ok(Self::Combined),
+            _ => Err(InvalidLogFormat(s.to_string())),
+        }
+    }
+}
+
+/// Middleware logging the requests in the combined log format:
+///
+/// `host - - [time] "method path version" status bytes "referer" "user-agent" duration_ms`
+pub async fn combined(req: Request, next: Next) -> Response {
+    let start = Instant::now();
+    // owned before awaiting, the future must be `Send`
+    let time = chrono::Local::now()
+        .format("%d/%b/%Y:%H:%M:%S %z")
+        .to_string();
+
+    let host = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map_or("-".to_string(), |x| x.0.ip().to_string());
+    let request_line = format!("{} {} {:?}", req.method(), req.uri(), req.version());
+    let (referer, user_agent) = {
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .unwrap_or("-")
+                .to_string()
+        };
+        (header(header::REFERER), header(header::USER_AGENT))
+    };
+
+    let res = next.run(req).await;
+
+    let bytes = res
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|x| x.to_str().ok())
+        .unwrap_or("-");
+
+    tracing::info!(
+        target: "access_log",
+        "{host} - - [{time}] \"{request_line}\" {} {bytes} \"{referer}\" \"{user_agent}\" {}",
+        res.status().as_u16(),
+        start.elapsed().as_millis(),
+    );
+
+    res
+}
@@ -0,0 +1,183 @@
+use std::{
+    fmt::Write as _,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::{Html, IntoResponse as _, Response},
+};
+use percent_encoding::{AsciiSet, CONTROLS};
+
+/// Characters encoded in the `href` of a name, so it stays a relative path of one segment,
+/// e.g. `a?b` is not a query and `javascript:x` is not a scheme
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b':')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'\\')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Directories under the prefixes listed when they have no `index.html`.
+#[derive(Debug, Clone)]
+pub struct AutoIndex {
+    served_dir_path: PathBuf,
+    /// Prefixes with trailing slash
+    prefixes: Vec<String>,
+}
+
+struct Entry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    modified: Option<chrono::DateTime<chrono::Local>>,
+}
+
+impl AutoIndex {
+    pub fn new(served_dir_path: impl AsRef<Path>, prefixes: &[String]) -> Self {
+        // the path is matched after `NormalizePathLayer` appended the trailing slash
+        let prefixes = prefixes
+            .iter()
+            .map(|x| {
+                if x.ends_with('/') {
+                    x.clone()
+                } else {
+                    format!("{x}/")
+                }
+            })
+            .collect();
+
+        Self {
+            served_dir_path: served_dir_path.as_ref().to_path_buf(),
+            prefixes,
+        }
+    }
+
+    fn is_listed(&self, path: &str) -> bool {
+        self.prefixes.iter().any(|x| path.starts_with(x.as_str()))
+    }
+
+    /// The directory of the request path, if it should be listed
+    fn dir_to_list(&self, path: &str) -> Option<PathBuf> {
+        if !self.is_listed(path) {
+            return None;
+        }
+
+        let rel_path = Path::new(path.trim_start_matches('/'));
+        // never list anything outside of the served dir
+        if !rel_path
+            .components()
+            .all(|x| matches!(x, Component::Normal(_)))
+        {
+            return None;
+        }
+
+        let dir = self.served_dir_path.join(rel_path);
+        (dir.is_dir() && !dir.join("index.html").exists()).then_some(dir)
+    }
+}
+
+/// Middleware listing the directories matched by [`AutoIndex`], others fall through unchanged.
+pub async fn autoindex(
+    State(autoindex): State<Arc<AutoIndex>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !matches!(*req.method(), Method::GET | Method::HEAD) {
+        return next.run(req).await;
+    }
+
+    let path = req.uri().path().to_string();
+    if !autoindex.is_listed(&path) {
+        return next.run(req).await;
+    }
+
+    // the file system is blocking
+    let listing = tokio::task::spawn_blocking(move || {
+        let dir = autoindex.dir_to_list(&path)?;
+        read_entries(&dir)
+            .inspect_err(|err| tracing::warn!("failed to list dir {}: {err}", dir.display()))
+            .ok()
+            .map(|entries| render(&path, &entries))
+    })
+    .await;
+
+    match listing {
+        Ok(Some(html)) => Html(html).into_response(),
+        Ok(None) => next.run(req).await,
+        Err(err) => {
+            tracing::error!("failed to list dir: {err}");
+            next.run(req).await
+        }
+    }
+}
+
+fn read_entries(dir: &Path) -> std::io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        entries.push(Entry {
+            name: entry.file_name().to_string_lossy().to_string(),
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            modified: metadata.modified().ok().map(Into::into),
+        });
+    }
+
+    // directories first
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+fn render(path: &str, entries: &[Entry]) -> String {
+    let path = escape(path);
+
+    let mut rows = String::new();
+    if path != "/" {
+        rows.push_str("<tr><td><a href=\"../\">../</a></td><td></td><td></td></tr>\n");
+    }
+    for entry in entries {
+        let href = percent_encoding::utf8_percent_encode(&entry.name, SEGMENT).to_string();
+        let href = escape(&href);
+        let name = escape(&entry.name);
+        let slash = if entry.is_dir { "/" } else { "" };
+        let size = if entry.is_dir {
+            "-".to_string()
+        } else {
+            entry.size.to_string()
+        };
+        let modified = entry
+            .modified
+            .map(|x| x.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let _ = writeln!(
+            rows,
+            "<tr><td><a href=\"{href}{slash}\">{name}{slash}</a></td><td>{size}</td><td>{modified}</td></tr>"
+        );
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Index of {path}</title></head>\n\
+         <body>\n<h1>Index of {path}</h1>\n<table>\n\
+         <tr><th>Name</th><th>Size</th><th>Modified</th></tr>\n{rows}</table>\n</body>\n</html>\n"
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
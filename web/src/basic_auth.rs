@@ -0,0 +1,110 @@
+use std::{str::FromStr, sync::Arc};
+
+use axum::{
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse as _, Response},
+};
+use base64::Engine as _;
+use subtle::ConstantTimeEq as _;
+
+/// Paths open without credentials, for the load balancers
+const OPEN_PATHS: &[&str] = &["/healthcheck/"];
+
+/// Credentials of HTTP Basic auth, parsed from `user:password` or `user:<bcrypt hash>`.
+#[derive(Clone)]
+pub struct BasicAuth {
+    user: String,
+    password: Password,
+}
+
+#[derive(Clone)]
+enum Password {
+    Plain(String),
+    Bcrypt(String),
+}
+
+#[derive(Debug)]
+pub struct InvalidBasicAuth;
+
+impl std::fmt::Display for InvalidBasicAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "expected `user:password` for basic auth")
+    }
+}
+
+impl std::error::Error for InvalidBasicAuth {}
+
+impl FromStr for BasicAuth {
+    type Err = InvalidBasicAuth;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (user, password) = s.split_once(':').ok_or(InvalidBasicAuth)?;
+
+        let password = if ["$2a$", "$2b$", "$2y$"]
+            .iter()
+            .any(|prefix| password.starts_with(prefix))
+        {
+            Password::Bcrypt(password.to_string())
+        } else {
+            Password::Plain(password.to_string())
+        };
+
+        Ok(Self {
+            user: user.to_string(),
+            password,
+        })
+    }
+}
+
+impl BasicAuth {
+    fn verify(&self, authorization: &str) -> bool {
+        let Some(encoded) = authorization.strip_prefix("Basic ") else {
+            return false;
+        };
+        let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(encoded.trim()) else {
+            return false;
+        };
+        let Some((user, password)) = std::str::from_utf8(&decoded)
+            .ok()
+            .and_then(|x| x.split_once(':'))
+        else {
+            return false;
+        };
+
+        let user_ok = bool::from(user.as_bytes().ct_eq(self.user.as_bytes()));
+        let password_ok = match &self.password {
+            Password::Plain(expected) => bool::from(password.as_bytes().ct_eq(expected.as_bytes())),
+            Password::Bcrypt(hash) => bcrypt::verify(password, hash).unwrap_or(false),
+        };
+
+        user_ok & password_ok
+    }
+}
+
+/// Middleware challenging the requests without valid credentials with `401`.
+pub async fn check(State(auth): State<Arc<BasicAuth>>, req: Request, next: Next) -> Response {
+    if OPEN_PATHS.contains(&req.uri().path()) {
+        return next.run(req).await;
+    }
+
+    let authorized = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(|x| auth.verify(x));
+
+    if authorized {
+        next.run(req).await
+    } else {
+        (
+            StatusCode::UNAUTHORIZED,
+            [(
+                header::WWW_AUTHENTICATE,
+                r#"Basic realm="my-site", charset="UTF-8""#,
+            )],
+        )
+            .into_response()
+    }
+}
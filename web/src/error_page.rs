@@ -0,0 +1,27 @@
+use std::{path::PathBuf, sync::Arc};
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{Html, IntoResponse as _, Response},
+};
+
+/// Middleware replacing the body of `500 Internal Server Error` responses,
+/// e.g. from IO errors of `ServeDir`, with the error page at `path`.
+///
+/// The status is kept, and the response is unchanged if the error page can't be read.
+pub async fn error_page(State(path): State<Arc<PathBuf>>, req: Request, next: Next) -> Response {
+    let res = next.run(req).await;
+    if res.status() != StatusCode::INTERNAL_SERVER_ERROR {
+        return res;
+    }
+
+    match tokio::fs::read_to_string(path.as_path()).await {
+        Ok(page) => (StatusCode::INTERNAL_SERVER_ERROR, Html(page)).into_response(),
+        Err(err) => {
+            tracing::warn!("failed to read error page {}: {err}", path.display());
+            res
+        }
+    }
+}
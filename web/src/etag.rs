@@ -0,0 +1,67 @@
+use std::hash::{DefaultHasher, Hash as _, Hasher as _};
+
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse as _, Response},
+};
+
+/// Middleware adding a weak `ETag` to the files served with `200 OK`,
+/// and responding `304 Not Modified` if it matches `If-None-Match`.
+///
+/// `ServeDir` only handles `If-Modified-Since` by itself, which is removed if
+/// `If-None-Match` is given as it takes precedence (RFC 9110 §13.2.2). The fallback
+/// not found page keeps its `404` status without an `ETag`.
+pub async fn etag(mut req: Request, next: Next) -> Response {
+    let if_none_match = req.headers().get(header::IF_NONE_MATCH).cloned();
+    if if_none_match.is_some() {
+        req.headers_mut().remove(header::IF_MODIFIED_SINCE);
+    }
+
+    let mut res = next.run(req).await;
+    if res.status() != StatusCode::OK {
+        return res;
+    }
+
+    let Some(etag) = weak_etag(res.headers()) else {
+        return res;
+    };
+
+    if if_none_match.is_some_and(|x| matches(&x, &etag)) {
+        let mut headers = HeaderMap::new();
+        if let Some(last_modified) = res.headers().get(header::LAST_MODIFIED) {
+            headers.insert(header::LAST_MODIFIED, last_modified.clone());
+        }
+        headers.insert(header::ETAG, etag);
+        return (StatusCode::NOT_MODIFIED, headers).into_response();
+    }
+
+    res.headers_mut().insert(header::ETAG, etag);
+    res
+}
+
+/// `W/"<hash>"` from the `Last-Modified` and `Content-Length` of the file
+fn weak_etag(headers: &HeaderMap) -> Option<HeaderValue> {
+    let last_modified = headers.get(header::LAST_MODIFIED)?;
+    let content_length = headers.get(header::CONTENT_LENGTH)?;
+
+    let mut hasher = DefaultHasher::new();
+    last_modified.as_bytes().hash(&mut hasher);
+    content_length.as_bytes().hash(&mut hasher);
+
+    HeaderValue::from_str(&format!("W/\"{:016x}\"", hasher.finish())).ok()
+}
+
+/// Weak comparison of `If-None-Match` with `etag`
+fn matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(if_none_match) = if_none_match.to_str() else {
+        return false;
+    };
+    let strip_weak = |x: &str| x.trim().trim_start_matches("W/").to_string();
+    let etag = strip_weak(etag.to_str().unwrap_or_default());
+
+    if_none_match
+        .split(',')
+        .any(|x| x.trim() == "*" || strip_weak(x) == etag)
+}
@@ -0,0 +1,38 @@
+use std::time::Instant;
+
+use anyhow::Context as _;
+use axum::{extract::Request, middleware::Next, response::Response};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+const REQUESTS_TOTAL: &str = "http_requests_total";
+const REQUEST_DURATION_SECONDS: &str = "http_request_duration_seconds";
+
+/// Buckets of the latency histogram, static files are mostly served within milliseconds
+const DURATION_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+/// Install the global Prometheus recorder, the handle renders the `/metrics` page.
+pub fn install_recorder() -> anyhow::Result<PrometheusHandle> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(REQUEST_DURATION_SECONDS.to_string()),
+            DURATION_BUCKETS,
+        )?
+        .install_recorder()
+        .context("failed to install the metrics recorder")
+}
+
+/// Middleware counting the requests by status code and recording the latency.
+pub async fn record(req: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = req.method().to_string();
+
+    let res = next.run(req).await;
+
+    let status = res.status().as_u16().to_string();
+    metrics::counter!(REQUESTS_TOTAL, "method" => method, "status" => status).increment(1);
+    metrics::histogram!(REQUEST_DURATION_SECONDS).record(start.elapsed().as_secs_f64());
+
+    res
+}
@@ -0,0 +1,482 @@
+use std::{path::Path, sync::Arc};
+
+use anyhow::Context as _;
+use axum::{
+    http::{HeaderValue, StatusCode, header},
+    response::{Redirect, Response},
+    routing::get,
+};
+use tower::Layer as _;
+use tower_http::{
+    normalize_path::NormalizePathLayer,
+    services::{ServeDir, ServeFile},
+    set_status::SetStatus,
+    trace::TraceLayer,
+};
+
+mod access_log;
+mod autoindex;
+mod basic_auth;
+mod error_page;
+mod etag;
+mod http_metrics;
+mod redirects;
+mod trailing_slash;
+
+pub use access_log::LogFormat;
+pub use basic_auth::BasicAuth;
+pub use http_metrics::install_recorder as install_metrics_recorder;
+pub use trailing_slash::{ServerSettings, TrailingSlash};
+
+/// The not found page in the output of the generator
+pub const NOT_FOUND_PAGE_FILE: &str = "not_found.html";
+/// The error page in the output of the generator
+pub const ERROR_PAGE_FILE: &str = "500.html";
+/// The [`ServerSettings`] in the output of the generator
+pub const SERVER_SETTINGS_FILE: &str = "server.json";
+
+/// Content Security Policy for the output of the generator: inline styles
+/// from syntax highlight, KaTeX from jsdelivr on the pages with math, and
+/// the inline script of the theme toggle by its hash.
+///
+/// The script and iframe of a comment system (giscus, utterances) are not allowed,
+/// override the policy with their origin in `script-src` and `frame-src`, see the README.
+pub const DEFAULT_CSP: &str = "default-src 'self'; \
+    script-src 'self' https://cdn.jsdelivr.net 'sha256-e2lQhFMMb6KoBMfkNDH6kVgeuY4+M384y1SCuROV8ko='; \
+    style-src 'self' 'unsafe-inline' https://cdn.jsdelivr.net; \
+    font-src 'self' https://cdn.jsdelivr.net; \
+    img-src 'self' https: data:; \
+    object-src 'none'; \
+    base-uri 'self'; \
+    frame-ancestors 'none'";
+
+/// Options of the app.
+#[derive(Clone)]
+pub struct AppOptions {
+    /// The `Content-Security-Policy` header of the HTML responses.
+    pub csp: String,
+    /// Serve `/metrics` with this handle, see [`install_metrics_recorder`].
+    pub metrics: Option<metrics_exporter_prometheus::PrometheusHandle>,
+    /// Require HTTP Basic auth except for the healthcheck.
+    pub basic_auth: Option<BasicAuth>,
+    /// Format of the access log.
+    pub log_format: LogFormat,
+    /// List the directories under these path prefixes (e.g. `/downloads`) without `index.html`.
+    pub autoindex_prefixes: Vec<String>,
+}
+
+impl Default for AppOptions {
+    fn default() -> Self {
+        Self {
+            csp: DEFAULT_CSP.to_string(),
+            metrics: None,
+            basic_auth: None,
+            log_format: LogFormat::default(),
+            autoindex_prefixes: Vec::new(),
+        }
+    }
+}
+
+/// The app serving the static files in `served_dir_path`.
+///
+/// Paths in `redirects.json` of `served_dir_path` are redirected before serving the files,
+/// and the trailing slash of the page urls follows `server.json`.
+///
+/// With the `base_path` of `server.json`, everything is served under it and `/` is
+/// redirected to it, the targets in `redirects.json` must include it.
+///
+/// `not_found_page_file_path` and `error_page_file_path` are relative to `served_dir_path`,
+/// the error page is served on internal errors with `500 Internal Server Error` status.
+pub fn app(
+    served_dir_path: impl AsRef<Path>,
+    not_found_page_file_path: impl AsRef<Path>,
+    error_page_file_path: impl AsRef<Path>,
+    options: &AppOptions,
+) -> anyhow::Result<axum::Router> {
+    let served_dir_path = served_dir_path.as_ref();
+
+    // serve the not found page, but always with `404 Not Found` status
+    let not_found_service = SetStatus::new(
+        ServeFile::new(served_dir_path.join(not_found_page_file_path)),
+        StatusCode::NOT_FOUND,
+    );
+    let serve_dir = ServeDir::new(served_dir_path).fallback(not_found_service);
+
+    let csp = HeaderValue::from_str(&options.csp).context("invalid content security policy")?;
+
+    let settings = ServerSettings::from_file(served_dir_path.join(SERVER_SETTINGS_FILE))?;
+
+    let redirects = redirects::Redirects::from_file(served_dir_path.join("redirects.json"))?;
+    if !redirects.is_empty() {
+        tracing::info!("loaded {} redirects", redirects.len());
+    }
+
+    let mut app = axum::Router::new()
+        .route("/healthcheck/", get(async || "healthy"))
+        .fallback_service(serve_dir)
+        .layer(axum::middleware::from_fn_with_state(
+            Arc::new(served_dir_path.join(error_page_file_path)),
+            error_page::error_page,
+        ));
+
+    if !options.autoindex_prefixes.is_empty() {
+        let autoindex = autoindex::AutoIndex::new(served_dir_path, &options.autoindex_prefixes);
+        app = app.layer(axum::middleware::from_fn_with_state(
+            Arc::new(autoindex),
+            autoindex::autoindex,
+        ));
+    }
+
+    let mut app = app
+        .layer(axum::middleware::from_fn(etag::etag))
+        .layer(axum::middleware::from_fn_with_state(
+            Arc::new(redirects),
+            redirects::redirect,
+        ))
+        .layer(axum::middleware::map_response(move |res| {
+            security_headers(res, csp.clone())
+        }));
+
+    app = match options.log_format {
+        LogFormat::Pretty => app.layer(TraceLayer::new_for_http()),
+        LogFormat::Combined => app.layer(axum::middleware::from_fn(access_log::combined)),
+    };
+
+    // added after the other layers, so requests to `/metrics` are not recorded
+    if let Some(handle) = options.metrics.clone() {
+        app = app
+            .layer(axum::middleware::from_fn(http_metrics::record))
+            .route("/metrics/", get(async move || handle.render()));
+    }
+
+    // after the metrics route, so it's protected too
+    if let Some(auth) = options.basic_auth.clone() {
+        app = app.layer(axum::middleware::from_fn_with_state(
+            Arc::new(auth),
+            basic_auth::check,
+        ));
+    }
+
+    // both forms are served, the other form of the policy is redirected before
+    let app = NormalizePathLayer::append_trailing_slash().layer(app);
+    let base_path = settings.base_path.trim_end_matches('/').to_string();
+    let policy = trailing_slash::Policy {
+        served_dir_path: served_dir_path.to_path_buf(),
+        trailing_slash: settings.trailing_slash,
+        base_path: base_path.clone(),
+    };
+    let app =
+        axum::Router::new()
+            .fallback_service(app)
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(policy),
+                trailing_slash::redirect,
+            ));
+
+    if base_path.is_empty() {
+        return Ok(app);
+    }
+
+    tracing::info!("serve under base path: {base_path}");
+    let root = format!("{base_path}/");
+    Ok(axum::Router::new()
+        .route("/", get(async move || Redirect::permanent(&root)))
+        .nest_service(&base_path, app))
+}
+
+/// Add `Content-Security-Policy` to the HTML responses, and `nosniff` to all.
+async fn security_headers(mut res: Response, csp: HeaderValue) -> Response {
+    let is_html = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(|x| x.starts_with("text/html"));
+
+    let headers = res.headers_mut();
+    if is_html {
+        headers.insert(header::CONTENT_SECURITY_POLICY, csp);
+    }
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use base64::Engine as _;
+    use tower::ServiceExt as _;
+
+    use super::*;
+
+    /// Output of the generator with a page and a stylesheet
+    fn served_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("index.html", "home");
+        write(NOT_FOUND_PAGE_FILE, "not found");
+        write(ERROR_PAGE_FILE, "error");
+        write("blog/abc/index.html", "abc");
+        write("static/a.css", "body {}");
+        dir
+    }
+
+    fn test_app(dir: &tempfile::TempDir, options: &AppOptions) -> axum::Router {
+        app(dir.path(), NOT_FOUND_PAGE_FILE, ERROR_PAGE_FILE, options).unwrap()
+    }
+
+    async fn get(
+        app: &axum::Router,
+        uri: &str,
+        headers: &[(header::HeaderName, &str)],
+    ) -> Response {
+        let mut req = Request::get(uri);
+        for (name, value) in headers {
+            req = req.header(name, *value);
+        }
+        app.clone()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn body(res: Response) -> String {
+        let bytes = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn metrics_behind_basic_auth() {
+        let dir = served_dir();
+        let handle = metrics_exporter_prometheus::PrometheusBuilder::new()
+            .build_recorder()
+            .handle();
+        let options = AppOptions {
+            metrics: Some(handle),
+            basic_auth: Some("user:pass".parse().unwrap()),
+            ..AppOptions::default()
+        };
+        let app = test_app(&dir, &options);
+
+        let res = get(&app, "/metrics/", &[]).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let credentials = base64::engine::general_purpose::STANDARD.encode("user:pass");
+        let authorization = format!("Basic {credentials}");
+        let res = get(
+            &app,
+            "/metrics/",
+            &[(header::AUTHORIZATION, &authorization)],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = get(&app, "/healthcheck/", &[]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn not_found_page_with_404() {
+        let dir = served_dir();
+        let app = test_app(&dir, &AppOptions::default());
+
+        let res = get(&app, "/missing/", &[]).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body(res).await, "not found");
+
+        let res = get(&app, "/static/missing.css", &[]).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        for uri in ["/", "/blog/abc/", "/static/a.css"] {
+            let res = get(&app, uri, &[]).await;
+            assert_eq!(res.status(), StatusCode::OK, "{uri}");
+        }
+        let res = get(&app, "/blog/abc/", &[]).await;
+        assert_eq!(body(res).await, "abc");
+    }
+
+    #[tokio::test]
+    async fn not_modified_on_conditional_request() {
+        let dir = served_dir();
+        let app = test_app(&dir, &AppOptions::default());
+
+        let res = get(&app, "/static/a.css", &[]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let etag = res.headers()[header::ETAG].to_str().unwrap().to_string();
+        assert!(etag.starts_with("W/\""), "{etag}");
+
+        let res = get(&app, "/static/a.css", &[(header::IF_NONE_MATCH, &etag)]).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers()[header::ETAG], etag.as_str());
+        assert!(body(res).await.is_empty());
+
+        let res = get(
+            &app,
+            "/static/a.css",
+            &[(header::IF_NONE_MATCH, "W/\"other\"")],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // `If-None-Match` takes precedence over a matching `If-Modified-Since`
+        let last_modified = res.headers()[header::LAST_MODIFIED].to_str().unwrap();
+        let last_modified = last_modified.to_string();
+        let res = get(
+            &app,
+            "/static/a.css",
+            &[
+                (header::IF_NONE_MATCH, "W/\"other\""),
+                (header::IF_MODIFIED_SINCE, &last_modified),
+            ],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = get(
+            &app,
+            "/static/a.css",
+            &[(header::IF_MODIFIED_SINCE, &last_modified)],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        let res = get(
+            &app,
+            "/static/a.css",
+            &[
+                (header::IF_NONE_MATCH, &etag),
+                (header::IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:00 GMT"),
+            ],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        // the not found page has no `ETag` and is never `304`
+        let res = get(&app, "/missing/", &[(header::IF_NONE_MATCH, "*")]).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(!res.headers().contains_key(header::ETAG));
+    }
+
+    #[test]
+    fn readme_csp_extends_the_default() {
+        let readme = include_str!("../README.md");
+        let start = readme.find("MY_SITE_WEB_CSP=\"").unwrap() + 17;
+        let example = &readme[start..start + readme[start..].find('"').unwrap()];
+
+        for source in DEFAULT_CSP.split_whitespace() {
+            assert!(example.contains(source), "`{source}` not in the README");
+        }
+    }
+
+    #[tokio::test]
+    async fn autoindex_listing() {
+        let dir = served_dir();
+        let names = ["a b.txt", "x\"<y>.txt", "q?#%.txt", "javascript:alert(1)"];
+        std::fs::create_dir_all(dir.path().join("downloads/sub")).unwrap();
+        for name in names {
+            std::fs::write(dir.path().join("downloads").join(name), "x").unwrap();
+        }
+        let options = AppOptions {
+            autoindex_prefixes: vec!["/downloads".to_string()],
+            ..Default::default()
+        };
+        let app = test_app(&dir, &options);
+
+        let res = get(&app, "/downloads/", &[]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let html = body(res).await;
+        for href in [
+            "sub/",
+            "a%20b.txt",
+            "x%22%3Cy%3E.txt",
+            "q%3F%23%25.txt",
+            "javascript%3Aalert(1)",
+        ] {
+            assert!(
+                html.contains(&format!("<a href=\"{href}\">")),
+                "{href}: {html}"
+            );
+        }
+        assert!(html.contains(">x&quot;&lt;y&gt;.txt</a>"), "{html}");
+        assert!(!html.contains("<y>"), "{html}");
+
+        // only under the prefixes
+        let res = get(&app, "/blog/", &[]).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn trailing_slash_policies() {
+        let dir = served_dir();
+        let app = test_app(&dir, &AppOptions::default());
+
+        let res = get(&app, "/blog/abc?q=1", &[]).await;
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(res.headers()[header::LOCATION], "/blog/abc/?q=1");
+        let res = get(&app, "/blog/abc/", &[]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        // files are never redirected
+        let res = get(&app, "/static/a.css", &[]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        // not to another host with a protocol-relative url
+        std::fs::create_dir(dir.path().join("evil.com")).unwrap();
+        let res = get(&app, "//evil.com", &[]).await;
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(res.headers()[header::LOCATION], "/evil.com/");
+        let res = get(&app, "///blog/abc", &[]).await;
+        assert_eq!(res.headers()[header::LOCATION], "/blog/abc/");
+
+        std::fs::write(
+            dir.path().join(SERVER_SETTINGS_FILE),
+            r#"{"trailing_slash": "never"}"#,
+        )
+        .unwrap();
+        let app = test_app(&dir, &AppOptions::default());
+
+        let res = get(&app, "/blog/abc/", &[]).await;
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(res.headers()[header::LOCATION], "/blog/abc");
+        let res = get(&app, "/blog/abc", &[]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body(res).await, "abc");
+        let res = get(&app, "//evil.com/", &[]).await;
+        assert_eq!(res.headers()[header::LOCATION], "/evil.com");
+        let res = get(&app, "/", &[]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn base_path() {
+        let dir = served_dir();
+        let app = test_app(&dir, &AppOptions::default());
+        let res = get(&app, "/blog/abc/", &[]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        std::fs::write(
+            dir.path().join(SERVER_SETTINGS_FILE),
+            r#"{"base_path": "/notes"}"#,
+        )
+        .unwrap();
+        let app = test_app(&dir, &AppOptions::default());
+
+        let res = get(&app, "/", &[]).await;
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(res.headers()[header::LOCATION], "/notes/");
+        let res = get(&app, "/notes/", &[]).await;
+        assert_eq!(body(res).await, "home");
+        let res = get(&app, "/notes/blog/abc/", &[]).await;
+        assert_eq!(body(res).await, "abc");
+        // the trailing slash redirect stays under the base path
+        let res = get(&app, "/notes/blog/abc", &[]).await;
+        assert_eq!(res.headers()[header::LOCATION], "/notes/blog/abc/");
+        let res = get(&app, "/blog/abc/", &[]).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,69 +1,143 @@
+use std::{future::IntoFuture as _, sync::Arc, time::Duration};
+
 use anyhow::Context as _;
-use axum::routing::get;
 use envconfig::Envconfig;
 use tokio::signal;
-use tower::Layer as _;
-use tower_http::{
-    normalize_path::NormalizePathLayer,
-    services::{ServeDir, ServeFile},
-    trace::TraceLayer,
-};
 use tracing_subscriber::{layer::SubscriberExt as _, util::SubscriberInitExt as _};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracking_setup();
-
     let config = Config::init_from_env().context("failed to get the config")?;
 
-    let addr = std::net::SocketAddrV4::new(config.addr, config.port);
-    let listener = tokio::net::TcpListener::bind(addr)
-        .await
+    tracking_setup(config.log_format);
+
+    let addr = std::net::SocketAddr::new(config.addr, config.port);
+    let listener = bind(addr, config.dual_stack)
         .with_context(|| format!("failed to listen on address: {}", addr))?;
 
     tracing::info!("serve at {}", addr);
 
-    let not_found_service = ServeFile::new(
-        config
-            .served_dir_path
-            .join(&config.not_found_page_file_path),
-    );
-    let serve_dir = ServeDir::new(&config.served_dir_path).not_found_service(not_found_service);
-
-    let app = axum::Router::new()
-        .route("/healthcheck/", get(async || "healthy"))
-        .fallback_service(serve_dir)
-        .layer(TraceLayer::new_for_http());
-
-    let app = NormalizePathLayer::append_trailing_slash().layer(app);
-    let app = axum::ServiceExt::<axum::extract::Request>::into_make_service(app);
-
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .context("failed to serve")
+    let metrics = if config.metrics_enabled {
+        Some(my_site_web::install_metrics_recorder()?)
+    } else {
+        None
+    };
+
+    let options = my_site_web::AppOptions {
+        csp: config
+            .csp
+            .unwrap_or_else(|| my_site_web::DEFAULT_CSP.to_string()),
+        metrics,
+        basic_auth: config.basic_auth,
+        log_format: config.log_format,
+        autoindex_prefixes: config
+            .autoindex_prefixes
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(str::to_string)
+            .collect(),
+    };
+    let app = my_site_web::app(
+        &config.served_dir_path,
+        &config.not_found_page_file_path,
+        &config.error_page_file_path,
+        &options,
+    )?;
+    let app = axum::ServiceExt::<axum::extract::Request>::into_make_service_with_connect_info::<
+        std::net::SocketAddr,
+    >(app);
+
+    // start the drain timeout once the shutdown signal is received
+    let shutdown = Arc::new(tokio::sync::Notify::new());
+    let server = axum::serve(listener, app).with_graceful_shutdown({
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown_signal().await;
+            shutdown.notify_one();
+        }
+    });
+
+    let timeout = Duration::from_secs(config.shutdown_timeout);
+    tokio::select! {
+        res = server.into_future() => {
+            res.context("failed to serve")?;
+            tracing::info!("shut down cleanly");
+        }
+        _ = async {
+            shutdown.notified().await;
+            tokio::time::sleep(timeout).await;
+        } => {
+            tracing::warn!("connections not drained in {timeout:?}, force shut down");
+        }
+    }
+
+    Ok(())
 }
 
 #[derive(Clone, Envconfig)]
 pub struct Config {
-    /// The IP address the server listens on.
+    /// The IP address the server listens on, IPv4 or IPv6 (e.g. `::`).
     #[envconfig(from = "MY_SITE_WEB_ADDR", default = "0.0.0.0")]
-    pub addr: std::net::Ipv4Addr,
+    pub addr: std::net::IpAddr,
+    /// Also accept IPv4 clients when listening on an IPv6 address, if the OS supports it.
+    #[envconfig(from = "MY_SITE_WEB_DUAL_STACK", default = "true")]
+    pub dual_stack: bool,
     /// The port the server listens on.
     #[envconfig(from = "MY_SITE_WEB_PORT", default = "5000")]
     pub port: u16,
     /// The directory path to serve files from.
     #[envconfig(from = "MY_SITE_WEB_SERVED_DIR_PATH", default = "/data")]
     pub served_dir_path: std::path::PathBuf,
-    /// The file to serve when a requested file is not found.
+    /// The file to serve when a requested file is not found, see `my_site_web::NOT_FOUND_PAGE_FILE`.
     #[envconfig(
         from = "MY_SITE_WEB_NOT_FOUND_PAGE_FILE_PATH",
         default = "not_found.html"
     )]
     pub not_found_page_file_path: std::path::PathBuf,
+    /// The file to serve on internal errors, see `my_site_web::ERROR_PAGE_FILE`.
+    #[envconfig(from = "MY_SITE_WEB_ERROR_PAGE_FILE_PATH", default = "500.html")]
+    pub error_page_file_path: std::path::PathBuf,
+    /// The `Content-Security-Policy` header of HTML responses, see `my_site_web::DEFAULT_CSP`.
+    #[envconfig(from = "MY_SITE_WEB_CSP")]
+    pub csp: Option<String>,
+    /// Seconds to wait for the in-flight requests on shutdown before force exiting.
+    #[envconfig(from = "MY_SITE_WEB_SHUTDOWN_TIMEOUT", default = "30")]
+    pub shutdown_timeout: u64,
+    /// Serve Prometheus metrics at `/metrics/`.
+    #[envconfig(from = "MY_SITE_WEB_METRICS_ENABLED", default = "false")]
+    pub metrics_enabled: bool,
+    /// Format of the logs, `pretty` or `combined` (Apache combined access log).
+    #[envconfig(from = "MY_SITE_WEB_LOG_FORMAT", default = "pretty")]
+    pub log_format: my_site_web::LogFormat,
+    /// Comma separated path prefixes whose directories without `index.html` are listed.
+    #[envconfig(from = "MY_SITE_WEB_AUTOINDEX_PREFIXES", default = "")]
+    pub autoindex_prefixes: String,
+    /// Require HTTP Basic auth with `user:password` or `user:<bcrypt hash>`.
+    #[envconfig(from = "MY_SITE_WEB_BASIC_AUTH")]
+    pub basic_auth: Option<my_site_web::BasicAuth>,
+}
+
+/// Listen on `addr`, with `IPV6_V6ONLY` off for IPv6 addresses if `dual_stack`.
+fn bind(addr: std::net::SocketAddr, dual_stack: bool) -> std::io::Result<tokio::net::TcpListener> {
+    let socket = socket2::Socket::new(
+        socket2::Domain::for_address(addr),
+        socket2::Type::STREAM,
+        Some(socket2::Protocol::TCP),
+    )?;
+
+    if addr.is_ipv6() {
+        socket.set_only_v6(!dual_stack)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+
+    tokio::net::TcpListener::from_std(socket.into())
 }
 
-fn tracking_setup() {
+fn tracking_setup(log_format: my_site_web::LogFormat) {
     let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
         .inspect_err(|err| {
             tracing::warn!(
@@ -73,14 +147,24 @@ fn tracking_setup() {
         })
         .unwrap_or_else(|_| "debug,hyper=off".into());
 
-    let fmt_layer = tracing_subscriber::fmt::layer()
-        .with_line_number(true)
-        .pretty();
-
-    tracing_subscriber::registry()
-        .with(env_filter)
-        .with(fmt_layer)
-        .init();
+    let registry = tracing_subscriber::registry().with(env_filter);
+
+    match log_format {
+        my_site_web::LogFormat::Pretty => {
+            let fmt_layer = tracing_subscriber::fmt::layer()
+                .with_line_number(true)
+                .pretty();
+            registry.with(fmt_layer).init();
+        }
+        // the access log lines carry their own time
+        my_site_web::LogFormat::Combined => {
+            let fmt_layer = tracing_subscriber::fmt::layer()
+                .with_target(false)
+                .without_time()
+                .compact();
+            registry.with(fmt_layer).init();
+        }
+    }
 }
 
 async fn shutdown_signal() {
@@ -0,0 +1,90 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use anyhow::Context as _;
+use axum::{
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse as _, Response},
+};
+use serde::Deserialize;
+
+/// Redirects read from `redirects.json` in the served dir, e.g.
+/// `{ "/old-path/": { "target": "/new-path/", "status": 308 } }`.
+#[derive(Debug, Clone, Default)]
+pub struct Redirects {
+    /// Path with trailing slash -> redirect
+    redirects: HashMap<String, Redirect>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Redirect {
+    target: String,
+    /// `301` by default
+    #[serde(default = "default_status")]
+    status: u16,
+}
+
+impl Redirects {
+    /// Empty if the file does not exist.
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if !path.try_exists()? {
+            return Ok(Self::default());
+        }
+
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let redirects: HashMap<String, Redirect> =
+            serde_json::from_reader(std::io::BufReader::new(file))
+                .with_context(|| format!("failed to parse {}", path.display()))?;
+
+        let mut ret = HashMap::new();
+        for (from, redirect) in redirects {
+            if !matches!(redirect.status, 301 | 302 | 307 | 308) {
+                return Err(anyhow::anyhow!(
+                    "invalid redirect status of {from}: {}",
+                    redirect.status
+                ));
+            }
+
+            // the path is matched after `NormalizePathLayer` appended the trailing slash
+            let from = if from.ends_with('/') {
+                from
+            } else {
+                format!("{from}/")
+            };
+            ret.insert(from, redirect);
+        }
+
+        Ok(Self { redirects: ret })
+    }
+
+    pub fn len(&self) -> usize {
+        self.redirects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.redirects.is_empty()
+    }
+}
+
+/// Middleware redirecting the matched paths, others fall through unchanged.
+pub async fn redirect(
+    State(redirects): State<Arc<Redirects>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    match redirects.redirects.get(req.uri().path()) {
+        Some(redirect) => {
+            let status =
+                StatusCode::from_u16(redirect.status).unwrap_or(StatusCode::MOVED_PERMANENTLY);
+            (status, [(header::LOCATION, redirect.target.clone())]).into_response()
+        }
+        None => next.run(req).await,
+    }
+}
+
+fn default_status() -> u16 {
+    301
+}
@@ -0,0 +1,96 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context as _;
+use axum::{
+    extract::{Request, State},
+    http::{Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse as _, Response},
+};
+use serde::{Deserialize, Serialize};
+
+/// Whether the page urls end with `/`, the other form is redirected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingSlash {
+    /// `/blog/abc/`
+    #[default]
+    Always,
+    /// `/blog/abc`
+    Never,
+}
+
+/// Settings of the server written by the generator to `server.json` in the served dir,
+/// so they are configured once in the site config.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ServerSettings {
+    #[serde(default)]
+    pub trailing_slash: TrailingSlash,
+    /// Path the site is served under (e.g. `/notes`), empty for the root
+    #[serde(default)]
+    pub base_path: String,
+}
+
+impl ServerSettings {
+    /// Default if the file does not exist.
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if !path.try_exists()? {
+            return Ok(Self::default());
+        }
+
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        serde_json::from_reader(std::io::BufReader::new(file))
+            .with_context(|| format!("failed to parse {}", path.display()))
+    }
+}
+
+pub struct Policy {
+    pub served_dir_path: PathBuf,
+    pub trailing_slash: TrailingSlash,
+    /// Prepended to the redirect targets, the app is nested under it
+    pub base_path: String,
+}
+
+/// Middleware redirecting the urls of directories in the served dir to the form
+/// of the policy, e.g. `/blog/abc` -> `/blog/abc/` for [`TrailingSlash::Always`].
+///
+/// Runs before `NormalizePathLayer`, which serves both forms by appending the slash.
+pub async fn redirect(State(policy): State<Arc<Policy>>, req: Request, next: Next) -> Response {
+    if !matches!(*req.method(), Method::GET | Method::HEAD) {
+        return next.run(req).await;
+    }
+
+    // `//evil.com/` would be a protocol-relative url in `Location`
+    let path = format!("/{}", req.uri().path().trim_start_matches('/'));
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return next.run(req).await;
+    }
+
+    let target = match policy.trailing_slash {
+        TrailingSlash::Always if !path.ends_with('/') => format!("{path}/"),
+        TrailingSlash::Never if path.ends_with('/') => trimmed.to_string(),
+        _ => return next.run(req).await,
+    };
+
+    // only pages, not the files or the routes like `/healthcheck`
+    let rel_path = Path::new(trimmed.trim_start_matches('/'));
+    let is_dir = rel_path
+        .components()
+        .all(|x| matches!(x, std::path::Component::Normal(_)))
+        && policy.served_dir_path.join(rel_path).is_dir();
+    if !is_dir {
+        return next.run(req).await;
+    }
+
+    let target = match req.uri().query() {
+        Some(query) => format!("{}{target}?{query}", policy.base_path),
+        None => format!("{}{target}", policy.base_path),
+    };
+    (StatusCode::PERMANENT_REDIRECT, [(header::LOCATION, target)]).into_response()
+}